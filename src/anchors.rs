@@ -0,0 +1,568 @@
+use std::sync::atomic::{AtomicU32, Ordering};
+use crate::JumpRope;
+
+/// Which way an [`Anchor`] moves when text is inserted exactly at its position.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Bias {
+    /// The anchor stays attached to the character before it. Text inserted at the anchor's
+    /// position ends up after the anchor.
+    Left,
+    /// The anchor stays attached to the character after it. Text inserted at the anchor's
+    /// position ends up before the anchor.
+    Right,
+}
+
+/// A handle to a position in a rope which is automatically updated as the rope is edited.
+///
+/// Anchors are created with [`JumpRope::create_anchor`], and their current position is read with
+/// [`JumpRope::anchor_pos`]. Anchors are only meaningful for the rope which created them (and
+/// clones of that rope).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Anchor {
+    /// The id of the AnchorSet which created this anchor.
+    set: u32,
+    slot: u32,
+    generation: u32,
+}
+
+const NIL: u32 = u32::MAX;
+
+/// Each anchor set gets a unique id, so anchors from other ropes can be recognised.
+static NEXT_SET_ID: AtomicU32 = AtomicU32::new(0);
+
+#[derive(Debug, Clone)]
+struct Node {
+    parent: u32,
+    left: u32,
+    right: u32,
+    /// Random priority. Each node has a higher priority than its children.
+    priority: u32,
+    /// The distance from the previous anchor to this one. (The first gap is measured from 0.)
+    gap: usize,
+    /// The sum of the gaps in this subtree.
+    sum: usize,
+    /// The number of anchors in this subtree.
+    size: u32,
+    bias: Bias,
+    generation: u32,
+    /// False if this slot is free.
+    live: bool,
+}
+
+/// The set of anchors in a rope.
+///
+/// Anchors are stored in position order in a treap (a randomly balanced binary tree). Their
+/// positions are stored implicitly as the gaps between adjacent anchors, so an edit can move every
+/// anchor after it by updating a single gap. Nodes also point to their parents, so an anchor's
+/// position can be found by walking up from its node.
+///
+/// Creating or removing an anchor and reading an anchor's position are *O(log n)*. Edits cost
+/// *O(log n)*, plus *O(k log n)* for the k anchors at the insert position or inside a deleted
+/// range.
+#[derive(Debug, Clone)]
+pub(crate) struct AnchorSet {
+    id: u32,
+    /// Tree nodes, indexed by anchor slot.
+    nodes: Vec<Node>,
+    root: u32,
+    free_slots: Vec<u32>,
+    /// xorshift state for generating priorities.
+    rng: u32,
+}
+
+impl Default for AnchorSet {
+    fn default() -> Self {
+        Self {
+            id: NEXT_SET_ID.fetch_add(1, Ordering::Relaxed),
+            nodes: Vec::new(),
+            root: NIL,
+            free_slots: Vec::new(),
+            rng: 0x9e3779b9,
+        }
+    }
+}
+
+impl AnchorSet {
+    pub(crate) fn is_empty(&self) -> bool {
+        self.root == NIL
+    }
+
+    fn len(&self) -> usize {
+        self.size(self.root)
+    }
+
+    fn size(&self, x: u32) -> usize {
+        if x == NIL { 0 } else { self.nodes[x as usize].size as usize }
+    }
+
+    fn sum(&self, x: u32) -> usize {
+        if x == NIL { 0 } else { self.nodes[x as usize].sum }
+    }
+
+    /// Recalculate the size and sum of x from its children, and point its children back at it.
+    fn update(&mut self, x: u32) {
+        let Node { left, right, gap, .. } = self.nodes[x as usize];
+        let size = 1 + self.size(left) + self.size(right);
+        let sum = gap + self.sum(left) + self.sum(right);
+        let n = &mut self.nodes[x as usize];
+        n.size = size as u32;
+        n.sum = sum;
+        for child in [left, right] {
+            if child != NIL { self.nodes[child as usize].parent = x; }
+        }
+    }
+
+    fn set_root(&mut self, x: u32) {
+        self.root = x;
+        if x != NIL { self.nodes[x as usize].parent = NIL; }
+    }
+
+    /// Join two trees, with all of the anchors in a before all of the anchors in b.
+    fn merge(&mut self, a: u32, b: u32) -> u32 {
+        if a == NIL { return b; }
+        if b == NIL { return a; }
+
+        if self.nodes[a as usize].priority > self.nodes[b as usize].priority {
+            let right = self.merge(self.nodes[a as usize].right, b);
+            self.nodes[a as usize].right = right;
+            self.update(a);
+            a
+        } else {
+            let left = self.merge(a, self.nodes[b as usize].left);
+            self.nodes[b as usize].left = left;
+            self.update(b);
+            b
+        }
+    }
+
+    /// Split the tree at x into a tree of its first n anchors, and a tree of the rest.
+    fn split(&mut self, x: u32, n: usize) -> (u32, u32) {
+        if x == NIL { return (NIL, NIL); }
+
+        let left = self.nodes[x as usize].left;
+        if n <= self.size(left) {
+            let (a, b) = self.split(left, n);
+            self.nodes[x as usize].left = b;
+            self.update(x);
+            (a, x)
+        } else {
+            let (a, b) = self.split(self.nodes[x as usize].right, n - self.size(left) - 1);
+            self.nodes[x as usize].right = a;
+            self.update(x);
+            (x, b)
+        }
+    }
+
+    /// Append the nodes in the tree at x to out, in order.
+    fn collect(&self, x: u32, out: &mut Vec<u32>) {
+        if x == NIL { return; }
+        let n = &self.nodes[x as usize];
+        self.collect(n.left, out);
+        out.push(x);
+        self.collect(n.right, out);
+    }
+
+    /// The node of the anchor with the specified index (in position order).
+    fn nth(&self, mut index: usize) -> u32 {
+        let mut x = self.root;
+        loop {
+            let n = &self.nodes[x as usize];
+            let left_size = self.size(n.left);
+            if index < left_size {
+                x = n.left;
+            } else if index == left_size {
+                return x;
+            } else {
+                index -= left_size + 1;
+                x = n.right;
+            }
+        }
+    }
+
+    fn pos_of(&self, mut x: u32) -> usize {
+        let n = &self.nodes[x as usize];
+        let mut pos = self.sum(n.left) + n.gap;
+        loop {
+            let parent = self.nodes[x as usize].parent;
+            if parent == NIL { return pos; }
+            let p = &self.nodes[parent as usize];
+            if p.right == x {
+                pos += self.sum(p.left) + p.gap;
+            }
+            x = parent;
+        }
+    }
+
+    fn index_of(&self, mut x: u32) -> usize {
+        let mut index = self.size(self.nodes[x as usize].left);
+        loop {
+            let parent = self.nodes[x as usize].parent;
+            if parent == NIL { return index; }
+            let p = &self.nodes[parent as usize];
+            if p.right == x {
+                index += self.size(p.left) + 1;
+            }
+            x = parent;
+        }
+    }
+
+    /// Add delta to the gap before x. Negative deltas are applied using wrapping arithmetic.
+    fn add_gap(&mut self, mut x: u32, delta: usize) {
+        self.nodes[x as usize].gap = self.nodes[x as usize].gap.wrapping_add(delta);
+        while x != NIL {
+            let n = &mut self.nodes[x as usize];
+            n.sum = n.sum.wrapping_add(delta);
+            x = n.parent;
+        }
+    }
+
+    /// Returns the number of anchors with a position <= pos, and the position of the last of those
+    /// anchors (or 0 if there are none).
+    fn count_at_or_before(&self, pos: usize) -> (usize, usize) {
+        let mut x = self.root;
+        let mut count = 0;
+        let mut last_pos = 0;
+        while x != NIL {
+            let n = &self.nodes[x as usize];
+            let node_pos = last_pos + self.sum(n.left) + n.gap;
+            if node_pos <= pos {
+                count += self.size(n.left) + 1;
+                last_pos = node_pos;
+                x = n.right;
+            } else {
+                x = n.left;
+            }
+        }
+        (count, last_pos)
+    }
+
+    fn count_before(&self, pos: usize) -> usize {
+        if pos == 0 { 0 } else { self.count_at_or_before(pos - 1).0 }
+    }
+
+    /// The node of an anchor, or None if the anchor has been removed or belongs to another set.
+    fn node_of(&self, anchor: Anchor) -> Option<u32> {
+        if anchor.set != self.id { return None; }
+        self.nodes.get(anchor.slot as usize)
+            .filter(|n| n.live && n.generation == anchor.generation)
+            .map(|_| anchor.slot)
+    }
+
+    fn create(&mut self, pos: usize, bias: Bias) -> Anchor {
+        self.rng ^= self.rng << 13;
+        self.rng ^= self.rng >> 17;
+        self.rng ^= self.rng << 5;
+
+        // New anchors go after any other anchors at the same position.
+        let (index, prev_pos) = self.count_at_or_before(pos);
+        let gap = pos - prev_pos;
+        if index < self.len() {
+            let next = self.nth(index);
+            self.add_gap(next, gap.wrapping_neg());
+        }
+
+        let node = Node {
+            parent: NIL,
+            left: NIL,
+            right: NIL,
+            priority: self.rng,
+            gap,
+            sum: gap,
+            size: 1,
+            bias,
+            generation: 0,
+            live: true,
+        };
+        let slot = match self.free_slots.pop() {
+            Some(slot) => {
+                let n = &mut self.nodes[slot as usize];
+                *n = Node { generation: n.generation, ..node };
+                slot
+            }
+            None => {
+                self.nodes.push(node);
+                (self.nodes.len() - 1) as u32
+            }
+        };
+
+        let (a, b) = self.split(self.root, index);
+        let a = self.merge(a, slot);
+        let root = self.merge(a, b);
+        self.set_root(root);
+
+        Anchor { set: self.id, slot, generation: self.nodes[slot as usize].generation }
+    }
+
+    fn remove(&mut self, anchor: Anchor) -> bool {
+        let x = match self.node_of(anchor) {
+            Some(x) => x,
+            None => return false,
+        };
+        let index = self.index_of(x);
+
+        // The next anchor's gap absorbs the removed one.
+        if index + 1 < self.len() {
+            let next = self.nth(index + 1);
+            self.add_gap(next, self.nodes[x as usize].gap);
+        }
+
+        let (a, rest) = self.split(self.root, index);
+        let (_, b) = self.split(rest, 1);
+        let root = self.merge(a, b);
+        self.set_root(root);
+
+        let n = &mut self.nodes[x as usize];
+        n.live = false;
+        n.generation = n.generation.wrapping_add(1);
+        self.free_slots.push(x);
+        true
+    }
+
+    fn pos(&self, anchor: Anchor) -> Option<usize> {
+        self.node_of(anchor).map(|x| self.pos_of(x))
+    }
+
+    /// Update the anchors after len characters were inserted at pos.
+    pub(crate) fn insert(&mut self, pos: usize, len: usize) {
+        if self.is_empty() || len == 0 { return; }
+
+        let start = self.count_before(pos);
+        let end = self.count_at_or_before(pos).0;
+
+        if start == end {
+            // No anchors at pos. Everything after it moves forward.
+            if end < self.len() {
+                let next = self.nth(end);
+                self.add_gap(next, len);
+            }
+            return;
+        }
+
+        // Anchors at exactly pos are split by their bias. Rebuild them in order with the left
+        // biased anchors (which stay put) first.
+        let (a, rest) = self.split(self.root, start);
+        let (group, b) = self.split(rest, end - start);
+
+        let mut nodes = Vec::with_capacity(end - start);
+        self.collect(group, &mut nodes);
+        let first_gap = self.nodes[nodes[0] as usize].gap;
+        let (mut left, right): (Vec<u32>, Vec<u32>) = nodes.into_iter()
+            .partition(|&x| self.nodes[x as usize].bias == Bias::Left);
+        let first_right = right.first().copied();
+        left.extend(right);
+
+        let mut group = NIL;
+        for (i, &x) in left.iter().enumerate() {
+            let n = &mut self.nodes[x as usize];
+            n.left = NIL;
+            n.right = NIL;
+            n.gap = if i == 0 { first_gap } else { 0 };
+            if Some(x) == first_right { n.gap += len; }
+            self.update(x);
+            group = self.merge(group, x);
+        }
+
+        let root = self.merge(a, group);
+        let root = self.merge(root, b);
+        self.set_root(root);
+
+        // If all of the anchors at pos stay put, the next anchor after them moves instead.
+        if first_right.is_none() && end < self.len() {
+            let next = self.nth(end);
+            self.add_gap(next, len);
+        }
+    }
+
+    /// Update the anchors after the characters in start..end were deleted.
+    pub(crate) fn remove_range(&mut self, start: usize, end: usize) {
+        if self.is_empty() || start >= end { return; }
+
+        // Anchors inside the deleted range (but not at the start of it) move to the start.
+        let first = self.count_at_or_before(start).0;
+        let last = self.count_at_or_before(end).0;
+        let next = (last < self.len()).then(|| self.nth(last));
+        let next_pos = next.map(|x| self.pos_of(x));
+
+        for i in first..last {
+            let x = self.nth(i);
+            let pos = self.pos_of(x);
+            self.add_gap(x, start.wrapping_sub(pos));
+        }
+
+        // And everything after the range moves back.
+        if let (Some(x), Some(next_pos)) = (next, next_pos) {
+            let cur_pos = self.pos_of(x);
+            self.add_gap(x, (next_pos - (end - start)).wrapping_sub(cur_pos));
+        }
+    }
+}
+
+/// Anchors are positions in the rope which are automatically updated when the rope is edited.
+/// They're useful for tracking cursors, bookmarks and diagnostics.
+///
+/// When text is inserted before an anchor, the anchor moves forward. When text containing an
+/// anchor is deleted, the anchor moves to the start of the deleted range. Text inserted exactly
+/// at an anchor's position moves the anchor if it has [`Bias::Right`].
+impl JumpRope {
+    /// Create an anchor at the specified unicode character position.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `pos` is past the end of the rope.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use jumprope::*;
+    /// let mut rope = JumpRope::from("hi there");
+    /// let a = rope.create_anchor(3, Bias::Left);
+    /// let b = rope.create_anchor(3, Bias::Right);
+    ///
+    /// rope.insert(0, "oh, ");
+    /// assert_eq!(rope.anchor_pos(a), Some(7));
+    ///
+    /// rope.insert(7, "you ");
+    /// assert_eq!(rope, "oh, hi you there");
+    /// assert_eq!(rope.anchor_pos(a), Some(7));
+    /// assert_eq!(rope.anchor_pos(b), Some(11));
+    ///
+    /// rope.remove(5..13);
+    /// assert_eq!(rope.anchor_pos(a), Some(5));
+    /// assert_eq!(rope.anchor_pos(b), Some(5));
+    /// ```
+    pub fn create_anchor(&mut self, pos: usize, bias: Bias) -> Anchor {
+        assert!(pos <= self.len_chars(), "Anchor position is past the end of the rope");
+        self.anchors.get_or_insert_with(Default::default).create(pos, bias)
+    }
+
+    /// Get the current unicode character position of an anchor. Returns `None` if the anchor has
+    /// been removed, or if it was created by a different rope.
+    pub fn anchor_pos(&self, anchor: Anchor) -> Option<usize> {
+        self.anchors.as_ref()?.pos(anchor)
+    }
+
+    /// Remove an anchor from the rope. Anchors which are no longer needed should be removed, as
+    /// each anchor adds a small cost to editing the rope.
+    ///
+    /// Returns false if the anchor was already removed, or if it was created by a different rope.
+    pub fn remove_anchor(&mut self, anchor: Anchor) -> bool {
+        self.anchors.as_mut().is_some_and(|anchors| anchors.remove(anchor))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::prelude::*;
+    use crate::{Bias, JumpRope};
+
+    #[test]
+    fn anchors_random_edits() {
+        let mut rng = SmallRng::seed_from_u64(321);
+        let mut rope = JumpRope::from("κόσμε κόσμε κόσμε");
+
+        // Track the expected position of each anchor by hand.
+        let mut anchors = vec![];
+
+        for _i in 0..1000 {
+            let len = rope.len_chars();
+            match rng.gen_range(0..10) {
+                0 | 1 => {
+                    let pos = rng.gen_range(0..=len);
+                    let bias = if rng.gen_bool(0.5) { Bias::Left } else { Bias::Right };
+                    anchors.push((rope.create_anchor(pos, bias), pos, bias));
+                }
+                2 if !anchors.is_empty() => {
+                    let (a, _, _) = anchors.swap_remove(rng.gen_range(0..anchors.len()));
+                    assert!(rope.remove_anchor(a));
+                }
+                3..=5 => {
+                    let pos = rng.gen_range(0..=len);
+                    let ins_len = rng.gen_range(1..4);
+                    rope.insert(pos, &"x".repeat(ins_len));
+                    for (_, p, bias) in anchors.iter_mut() {
+                        if *p > pos || (*p == pos && *bias == Bias::Right) { *p += ins_len; }
+                    }
+                }
+                _ => {
+                    let start = rng.gen_range(0..=len);
+                    let end = rng.gen_range(start..=len.min(start + 5));
+                    rope.remove(start..end);
+                    for (_, p, _) in anchors.iter_mut() {
+                        if *p > end { *p -= end - start; } else if *p > start { *p = start; }
+                    }
+                }
+            }
+
+            for &(a, p, _) in &anchors {
+                assert_eq!(rope.anchor_pos(a), Some(p));
+            }
+        }
+    }
+
+    #[test]
+    fn anchors_split_append() {
+        let mut rope = JumpRope::from("hello world");
+        let a = rope.create_anchor(8, Bias::Left);
+        let b = rope.create_anchor(11, Bias::Right);
+        let c = rope.create_anchor(11, Bias::Left);
+
+        let rest = rope.split_off(5);
+        assert_eq!(rope.anchor_pos(a), Some(5));
+
+        rope.append(rest);
+        assert_eq!(rope.anchor_pos(a), Some(5));
+        assert_eq!(rope.anchor_pos(b), Some(11));
+        assert_eq!(rope.anchor_pos(c), Some(5));
+
+        let cloned = rope.clone();
+        assert_eq!(cloned.anchor_pos(b), Some(11));
+    }
+
+    #[test]
+    fn anchors_at_same_position() {
+        let mut rope = JumpRope::from("abc");
+        let biases = [Bias::Right, Bias::Left, Bias::Right, Bias::Left];
+        let group = biases.map(|bias| rope.create_anchor(1, bias));
+        let after = rope.create_anchor(2, Bias::Left);
+
+        rope.insert(1, "xx");
+        assert_eq!(group.map(|a| rope.anchor_pos(a).unwrap()), [3, 1, 3, 1]);
+        assert_eq!(rope.anchor_pos(after), Some(4));
+
+        // Removing anchors from the middle of the group leaves the others in place.
+        assert!(rope.remove_anchor(group[1]));
+        assert!(rope.remove_anchor(group[2]));
+        rope.insert(1, "y");
+        assert_eq!(rope.anchor_pos(group[0]), Some(4));
+        assert_eq!(rope.anchor_pos(group[3]), Some(1));
+        assert_eq!(rope.anchor_pos(after), Some(5));
+
+        rope.remove(0..5);
+        assert_eq!([group[0], group[3], after].map(|a| rope.anchor_pos(a).unwrap()), [0, 0, 0]);
+    }
+
+    #[test]
+    fn stale_and_foreign_anchors() {
+        let mut rope = JumpRope::from("hi");
+        let a = rope.create_anchor(1, Bias::Left);
+        assert!(rope.remove_anchor(a));
+        assert!(!rope.remove_anchor(a));
+
+        // The new anchor reuses a's slot, but a still isn't valid.
+        let b = rope.create_anchor(2, Bias::Left);
+        assert_eq!(rope.anchor_pos(a), None);
+        assert_eq!(rope.anchor_pos(b), Some(2));
+
+        // A rope which has never had any anchors.
+        let mut other = JumpRope::from("hi");
+        assert_eq!(other.anchor_pos(b), None);
+        assert!(!other.remove_anchor(b));
+
+        // Anchors from another rope aren't recognised, even if they name a live slot.
+        let c = other.create_anchor(0, Bias::Left);
+        assert_eq!(rope.anchor_pos(c), None);
+        assert_eq!(other.anchor_pos(b), None);
+        assert!(!rope.remove_anchor(c));
+        assert_eq!(other.anchor_pos(c), Some(0));
+    }
+}
@@ -0,0 +1,202 @@
+use std::fmt::{Display, Formatter};
+use std::ops::Range;
+use crate::JumpRope;
+
+/// An error returned by [`JumpRope::apply_edits`] when the edits aren't sorted, or overlap one
+/// another.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct EditOrderError {
+    /// The index of the first edit which is out of order.
+    pub index: usize,
+}
+
+impl Display for EditOrderError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "edit {} is out of order or overlaps the previous edit", self.index)
+    }
+}
+
+impl std::error::Error for EditOrderError {}
+
+fn first_unsorted(edits: &[(Range<usize>, &str)], ascending: bool) -> Option<usize> {
+    edits.windows(2).position(|w| {
+        if ascending { w[0].0.end > w[1].0.start } else { w[1].0.end > w[0].0.start }
+    }).map(|i| i + 1)
+}
+
+impl JumpRope {
+    /// Apply a batch of edits to the rope. Each edit replaces a range of unicode characters with
+    /// new content (like [`replace`](Self::replace)).
+    ///
+    /// All ranges name positions in the original document, before any of the edits are applied.
+    /// The edits must be sorted, in either ascending or descending order, and must not overlap.
+    /// (Multiple inserts at the same position are inserted in the order they're listed.)
+    ///
+    /// The edits are applied using a single cursor which moves forward through the rope. This is
+    /// much faster than calling `replace` for each edit, which would search for each position
+    /// from the start of the rope.
+    ///
+    /// Returns an error if the edits are out of order. In that case the rope is not modified.
+    ///
+    /// # Panics
+    ///
+    /// Panics if any of the ranges extend past the end of the rope.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use jumprope::*;
+    /// let mut rope = JumpRope::from("one two three");
+    /// rope.apply_edits([(0..3, "1"), (4..7, "2"), (8..13, "3")]).unwrap();
+    /// assert_eq!(rope, "1 2 3");
+    ///
+    /// assert!(rope.apply_edits([(4..5, "x"), (0..1, "x"), (2..3, "x")]).is_err());
+    /// ```
+    pub fn apply_edits<'s, I: IntoIterator<Item = (Range<usize>, &'s str)>>(&mut self, edits: I) -> Result<(), EditOrderError> {
+        let mut edits: Vec<(Range<usize>, &'s str)> = edits.into_iter().collect();
+
+        let len = self.len_chars();
+        for (range, _) in &edits {
+            assert!(range.start <= range.end && range.end <= len, "Edit range {:?} is out of bounds", range);
+        }
+
+        if let Some(asc_index) = first_unsorted(&edits, true) {
+            if let Some(desc_index) = first_unsorted(&edits, false) {
+                // Report the error relative to the direction of the first edits which differ.
+                let looks_ascending = match edits.windows(2).find(|w| w[0].0 != w[1].0) {
+                    Some(w) => (w[0].0.start, w[0].0.end) < (w[1].0.start, w[1].0.end),
+                    None => true,
+                };
+                let index = if looks_ascending { asc_index } else { desc_index };
+                return Err(EditOrderError { index });
+            }
+
+            // Reverse the edits, but keep inserts at the same position in the order they're listed.
+            edits.reverse();
+            let mut i = 0;
+            while i < edits.len() {
+                let mut j = i + 1;
+                while j < edits.len() && edits[i].0.is_empty() && edits[j].0 == edits[i].0 { j += 1; }
+                edits[i..j].reverse();
+                i = j;
+            }
+        }
+
+        let mut cursor = self.mut_cursor_at_char(0, true);
+        // The position of the cursor in the original document.
+        let mut pos = 0;
+        for (range, content) in edits {
+            cursor.advance_by_chars(range.start - pos);
+            Self::replace_at_cursor(&mut cursor, range.end - range.start, content, None);
+            pos = range.end;
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::prelude::*;
+    use crate::JumpRope;
+    use crate::test_utils::{random_content, SAMPLE};
+    use super::*;
+
+    #[test]
+    fn apply_edits_order() {
+        let mut rope = JumpRope::from("abcdef");
+        rope.apply_edits([(1..1, "x"), (1..1, "y"), (1..2, "B")]).unwrap();
+        assert_eq!(rope, "axyBcdef");
+
+        rope.apply_edits([(6..8, ""), (2..2, "-"), (0..1, "A")]).unwrap();
+        assert_eq!(rope, "Ax-yBcd");
+
+        assert_eq!(rope.apply_edits([(0..2, ""), (1..3, "")]), Err(EditOrderError { index: 1 }));
+        assert_eq!(rope.apply_edits([(3..4, ""), (1..2, ""), (2..3, "")]), Err(EditOrderError { index: 2 }));
+        assert_eq!(rope, "Ax-yBcd");
+
+        // Descending edits keep inserts at the same position in the order they're listed too.
+        let mut rope = JumpRope::from("abcdef");
+        rope.apply_edits([(4..4, "x"), (1..3, "B"), (1..1, "p"), (1..1, "q"), (0..0, "<")]).unwrap();
+        assert_eq!(rope, "<apqBdxef");
+
+        // Errors are reported in the direction the edits appear to be sorted.
+        assert_eq!(rope.apply_edits([(5..5, ""), (5..5, ""), (1..1, ""), (3..3, "")]), Err(EditOrderError { index: 3 }));
+        assert_eq!(rope.apply_edits([(1..1, ""), (1..1, ""), (5..5, ""), (3..3, "")]), Err(EditOrderError { index: 3 }));
+        assert_eq!(rope.apply_edits([(2..4, ""), (2..2, ""), (3..4, "")]), Err(EditOrderError { index: 2 }));
+
+        rope.apply_edits(std::iter::empty()).unwrap();
+        assert_eq!(rope, "<apqBdxef");
+    }
+
+    #[test]
+    fn apply_edits_empty_rope() {
+        let mut rope = JumpRope::new();
+        rope.apply_edits([(0..0, "a"), (0..0, "𝕐"), (0..0, "")]).unwrap();
+        assert_eq!(rope, "a𝕐");
+
+        let mut rope = JumpRope::new();
+        rope.apply_edits([(0..0, "")]).unwrap();
+        rope.check();
+        assert!(rope.is_empty());
+    }
+
+    #[test]
+    fn apply_edits_at_the_end() {
+        let mut rope = JumpRope::from("κόσμε");
+        rope.apply_edits([(5..5, "!"), (5..5, "?")]).unwrap();
+        assert_eq!(rope, "κόσμε!?");
+
+        // Deleting up to the end, listed in descending order.
+        rope.apply_edits([(7..7, "."), (3..7, ""), (0..1, "")]).unwrap();
+        rope.check();
+        assert_eq!(rope, "όσ.");
+
+        rope.apply_edits([(0..3, "")]).unwrap();
+        assert!(rope.is_empty());
+    }
+
+    #[test]
+    #[should_panic]
+    fn apply_edits_past_end() {
+        JumpRope::from("abc").apply_edits([(0..1, "x"), (3..4, "")]).unwrap();
+    }
+
+    #[test]
+    #[should_panic]
+    #[allow(clippy::reversed_empty_ranges)]
+    fn apply_edits_reversed_range() {
+        JumpRope::from("abc").apply_edits([(2..1, "")]).unwrap();
+    }
+
+    #[test]
+    fn apply_random_edits() {
+        let mut rng = SmallRng::seed_from_u64(3);
+        for _i in 0..200 {
+            let mut rope = JumpRope::from(SAMPLE);
+            let len = rope.len_chars();
+
+            let mut edits = vec![];
+            let mut pos = 0;
+            while pos < len && rng.gen_bool(0.9) {
+                // Edits don't touch, so the order is unambiguous when they're reversed.
+                let min = if edits.is_empty() { 0 } else { pos + 1 };
+                let start = rng.gen_range(min..=len.min(pos + 20));
+                let end = rng.gen_range(start..=len.min(start + 10));
+                edits.push((start..end, random_content(&mut rng)));
+                pos = end;
+            }
+
+            // Applying the edits in reverse order one by one gives the expected result.
+            let mut expected = rope.clone();
+            for (range, content) in edits.iter().rev() {
+                expected.replace(range.clone(), content);
+            }
+
+            if rng.gen_bool(0.5) { edits.reverse(); }
+            rope.apply_edits(edits).unwrap();
+            rope.check();
+            assert_eq!(rope, expected);
+        }
+    }
+}
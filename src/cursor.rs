@@ -0,0 +1,601 @@
+use std::fmt::{Debug, Formatter};
+use crate::fast_str_tools::count_chars;
+use crate::iter::{half_chars, PrevNodes};
+use crate::jumprope::{MutCursor, Node};
+use crate::utils::str_chars_to_bytes;
+use crate::JumpRope;
+
+/// A cursor for making a series of edits to a rope. Created with [`JumpRope::cursor_at`].
+///
+/// The cursor remembers its position in the rope's skip list, so edits at (or just after) the
+/// cursor don't need to search from the start of the rope. This makes it much faster to edit a
+/// document from left to right using a cursor than by calling [`JumpRope::insert`] and
+/// [`JumpRope::remove`] repeatedly.
+///
+/// Moves (in either direction) only search as much of the skip list as they need to, so short
+/// moves are cheap. Moving the cursor by `d` characters takes *O(log d)* time, rather than the
+/// *O(log n)* time needed to search from the start of the rope.
+///
+/// # Example
+///
+/// ```
+/// # use jumprope::*;
+/// let mut rope = JumpRope::from("a c");
+/// let mut cursor = rope.cursor_at(1);
+/// cursor.insert(" b");
+/// cursor.move_by(2);
+/// cursor.insert(" d");
+/// assert_eq!(cursor.pos(), 7);
+///
+/// cursor.move_by(-4);
+/// cursor.delete_forward(2);
+/// cursor.delete_backward(2);
+/// assert_eq!(cursor.pos(), 1);
+/// assert_eq!(rope, "a d");
+/// ```
+pub struct EditCursor<'a> {
+    cursor: MutCursor<'a>,
+}
+
+impl JumpRope {
+    /// Create an editing cursor at the specified unicode character position. See
+    /// [`EditCursor`].
+    ///
+    /// # Panics
+    ///
+    /// Panics if `pos` is past the end of the rope.
+    pub fn cursor_at(&mut self, pos: usize) -> EditCursor<'_> {
+        assert!(pos <= self.len_chars(), "Cursor position is past the end of the rope");
+        EditCursor { cursor: self.mut_cursor_at_char(pos, true) }
+    }
+}
+
+impl<'a> EditCursor<'a> {
+    /// The cursor's position in the rope, in unicode characters.
+    pub fn pos(&self) -> usize {
+        self.cursor.global_char_pos()
+    }
+
+    /// The length of the rope, in unicode characters.
+    pub fn len_chars(&self) -> usize {
+        self.cursor.totals().skip_chars
+    }
+
+    /// Insert content at the cursor. The cursor is moved to the end of the inserted text.
+    pub fn insert(&mut self, content: &str) {
+        JumpRope::insert_at_cursor(&mut self.cursor, content);
+    }
+
+    /// Delete the `n` characters after the cursor, like pressing the delete key.
+    ///
+    /// # Panics
+    ///
+    /// Panics if there are less than `n` characters after the cursor.
+    pub fn delete_forward(&mut self, n: usize) {
+        assert!(self.pos() + n <= self.len_chars(), "Cannot delete past the end of the rope");
+        JumpRope::del_at_cursor(&mut self.cursor, n, None);
+    }
+
+    /// Delete the `n` characters before the cursor, like pressing backspace.
+    ///
+    /// # Panics
+    ///
+    /// Panics if there are less than `n` characters before the cursor.
+    pub fn delete_backward(&mut self, n: usize) {
+        if n == 0 { return; }
+        let pos = self.pos();
+        assert!(n <= pos, "Cannot delete past the start of the rope");
+        self.cursor.retreat_by_chars(n);
+        JumpRope::del_at_cursor(&mut self.cursor, n, None);
+    }
+
+    /// Move the cursor forwards (or backwards, if `by` is negative) by the specified number of
+    /// unicode characters.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the cursor would move outside of the rope.
+    pub fn move_by(&mut self, by: isize) {
+        if by >= 0 {
+            self.cursor.advance_by_chars(by as usize);
+        } else {
+            self.cursor.retreat_by_chars(by.unsigned_abs());
+        }
+    }
+
+    /// Move the cursor to the specified unicode character position.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `pos` is past the end of the rope.
+    pub fn move_to(&mut self, pos: usize) {
+        let current = self.pos();
+        if pos >= current {
+            self.cursor.advance_by_chars(pos - current);
+        } else {
+            self.cursor.retreat_by_chars(current - pos);
+        }
+    }
+}
+
+impl<'a> Debug for EditCursor<'a> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("EditCursor")
+            .field("pos", &self.pos())
+            .finish()
+    }
+}
+
+/// A cursor for reading a rope, which can move forwards and backwards through the document by
+/// characters, lines and chunks. Created with [`JumpRope::read_cursor_at`].
+///
+/// The rope's content is stored in chunks. The cursor reads from the chunk it's currently in, so
+/// reading in either direction is cheap. Stepping into the next or previous chunk takes *O(1)*
+/// amortized time. (Nodes only link forwards, so the cursor finds previous chunks in batches.)
+/// Jumping outside of the current chunk with [`move_to`](Self::move_to) searches the rope's skip
+/// list, which takes *O(log n)* time.
+///
+/// # Example
+///
+/// ```
+/// # use jumprope::*;
+/// let rope = JumpRope::from("hi\nthere");
+/// let mut cursor = rope.read_cursor_at(1);
+/// assert_eq!(cursor.next_char(), Some('i'));
+/// assert!(cursor.next_line());
+/// assert_eq!(cursor.pos(), 3);
+/// assert_eq!(cursor.prev_char(), Some('\n'));
+///
+/// cursor.move_by(4);
+/// assert_eq!(cursor.next_char(), Some('r'));
+/// ```
+#[derive(Clone)]
+pub struct RopeCursor<'a> {
+    rope: &'a JumpRope,
+    node: &'a Node,
+    /// Set if the current chunk is the second half of the node's gap buffer.
+    in_end: bool,
+    chunk: &'a str,
+    /// The char position of the start of the current chunk.
+    chunk_start: usize,
+    /// The byte offset of the cursor in the current chunk.
+    offset: usize,
+    /// The char position of the cursor.
+    pos: usize,
+    /// The nodes before the current node, for stepping backwards.
+    prev_nodes: PrevNodes<'a>,
+}
+
+impl JumpRope {
+    /// Create a cursor for reading the rope, starting at the specified unicode character position.
+    /// See [`RopeCursor`].
+    ///
+    /// # Panics
+    ///
+    /// Panics if `pos` is past the end of the rope.
+    pub fn read_cursor_at(&self, pos: usize) -> RopeCursor<'_> {
+        let mut cursor = RopeCursor {
+            rope: self,
+            node: &self.head,
+            in_end: false,
+            chunk: "",
+            chunk_start: 0,
+            offset: 0,
+            pos: 0,
+            prev_nodes: PrevNodes::default(),
+        };
+        cursor.seek(pos);
+        cursor
+    }
+}
+
+impl<'a> RopeCursor<'a> {
+    /// Move the cursor to pos by searching from the head of the rope.
+    fn seek(&mut self, pos: usize) {
+        let cursor = self.rope.read_cursor_at_char(pos, false);
+        let node = cursor.node;
+        let local = cursor.offset_chars;
+        let gap_start = node.str.gap_start_chars as usize;
+        let end = node.str.end_as_str();
+
+        self.node = node;
+        self.pos = pos;
+        self.prev_nodes.clear();
+        if local < gap_start || end.is_empty() {
+            self.in_end = false;
+            self.chunk = node.str.start_as_str();
+            self.chunk_start = pos - local;
+            self.offset = str_chars_to_bytes(self.chunk, local);
+        } else {
+            self.in_end = true;
+            self.chunk = end;
+            self.chunk_start = pos - (local - gap_start);
+            self.offset = str_chars_to_bytes(end, local - gap_start);
+        }
+    }
+
+    fn chunk_chars(&self) -> usize {
+        half_chars(self.node, self.in_end)
+    }
+
+    /// Move to the start of the next non-empty chunk. Returns false at the end of the rope.
+    fn step_forward(&mut self) -> bool {
+        let start = self.chunk_start + self.chunk_chars();
+        let mut node = self.node;
+        let mut in_end = self.in_end;
+
+        loop {
+            let chunk = if !in_end {
+                in_end = true;
+                node.str.end_as_str()
+            } else {
+                match unsafe { node.next_ptr().as_ref() } {
+                    Some(next) => {
+                        self.prev_nodes.push(node);
+                        node = next;
+                        in_end = false;
+                        node.str.start_as_str()
+                    }
+                    None => return false,
+                }
+            };
+
+            if !chunk.is_empty() {
+                self.node = node;
+                self.in_end = in_end;
+                self.chunk = chunk;
+                self.chunk_start = start;
+                self.offset = 0;
+                self.pos = start;
+                return true;
+            }
+        }
+    }
+
+    /// Move to the end of the previous non-empty chunk. Returns false at the start of the rope.
+    fn step_back(&mut self) -> bool {
+        let end = self.chunk_start;
+        let mut start = end;
+        let mut node = self.node;
+        let mut in_end = self.in_end;
+
+        loop {
+            if in_end {
+                in_end = false;
+            } else {
+                // start is the start of node here.
+                match self.prev_nodes.prev(self.rope, node, start) {
+                    Some(prev) => {
+                        node = prev;
+                        in_end = true;
+                    }
+                    None => return false,
+                }
+            }
+
+            start -= half_chars(node, in_end);
+            let chunk = if in_end { node.str.end_as_str() } else { node.str.start_as_str() };
+            if !chunk.is_empty() {
+                self.node = node;
+                self.in_end = in_end;
+                self.chunk = chunk;
+                self.chunk_start = start;
+                self.offset = chunk.len();
+                self.pos = end;
+                return true;
+            }
+        }
+    }
+
+    /// The cursor's position in the rope, in unicode characters.
+    pub fn pos(&self) -> usize {
+        self.pos
+    }
+
+    /// The length of the rope, in unicode characters.
+    pub fn len_chars(&self) -> usize {
+        self.rope.len_chars()
+    }
+
+    /// The chunk of the rope which the cursor is in. When the cursor is at the boundary between two
+    /// chunks, this may return either chunk.
+    pub fn chunk(&self) -> &'a str {
+        self.chunk
+    }
+
+    /// The unicode character position of the start of [`chunk`](Self::chunk).
+    pub fn chunk_start(&self) -> usize {
+        self.chunk_start
+    }
+
+    /// Read the character after the cursor, and move the cursor past it. Returns `None` at the
+    /// end of the rope.
+    pub fn next_char(&mut self) -> Option<char> {
+        if self.offset == self.chunk.len() && !self.step_forward() { return None; }
+        let c = self.chunk[self.offset..].chars().next().unwrap();
+        self.offset += c.len_utf8();
+        self.pos += 1;
+        Some(c)
+    }
+
+    /// Read the character before the cursor, and move the cursor back before it. Returns `None`
+    /// at the start of the rope.
+    pub fn prev_char(&mut self) -> Option<char> {
+        if self.offset == 0 && !self.step_back() { return None; }
+        let c = self.chunk[..self.offset].chars().next_back().unwrap();
+        self.offset -= c.len_utf8();
+        self.pos -= 1;
+        Some(c)
+    }
+
+    /// Read the rest of the current chunk after the cursor, and move the cursor to the end of it.
+    /// Calling this repeatedly reads the rest of the rope. Returns `None` at the end of the rope.
+    pub fn next_chunk(&mut self) -> Option<&'a str> {
+        if self.offset == self.chunk.len() && !self.step_forward() { return None; }
+        let s = &self.chunk[self.offset..];
+        self.offset = self.chunk.len();
+        self.pos += count_chars(s);
+        Some(s)
+    }
+
+    /// Read the part of the current chunk before the cursor, and move the cursor to the start of
+    /// it. Returns `None` at the start of the rope.
+    pub fn prev_chunk(&mut self) -> Option<&'a str> {
+        if self.offset == 0 && !self.step_back() { return None; }
+        let s = &self.chunk[..self.offset];
+        self.offset = 0;
+        self.pos = self.chunk_start;
+        Some(s)
+    }
+
+    /// Move the cursor to the specified unicode character position. Moves within the current chunk
+    /// are cheap. Otherwise this takes *O(log n)* time.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `pos` is past the end of the rope.
+    pub fn move_to(&mut self, pos: usize) {
+        if pos >= self.chunk_start && pos <= self.chunk_start + self.chunk_chars() {
+            self.offset = str_chars_to_bytes(self.chunk, pos - self.chunk_start);
+            self.pos = pos;
+        } else {
+            self.seek(pos);
+        }
+    }
+
+    /// Move the cursor forwards (or backwards, if `by` is negative) by the specified number of
+    /// unicode characters. See [`move_to`](Self::move_to).
+    ///
+    /// # Panics
+    ///
+    /// Panics if the cursor would move outside of the rope.
+    pub fn move_by(&mut self, by: isize) {
+        let pos = self.pos.checked_add_signed(by)
+            .expect("Cannot move cursor past the start of the rope");
+        self.move_to(pos);
+    }
+
+    /// Move the cursor to the start of the next line. If there are no more lines, the cursor is
+    /// moved to the end of the rope and this returns false.
+    pub fn next_line(&mut self) -> bool {
+        while let Some(s) = self.next_chunk() {
+            if let Some(i) = s.find('\n') {
+                // Move back to just after the newline.
+                let rest = &s[i + 1..];
+                self.offset -= rest.len();
+                self.pos -= count_chars(rest);
+                return true;
+            }
+        }
+        false
+    }
+
+    /// Move the cursor to the start of the current line.
+    pub fn line_start(&mut self) {
+        while let Some(s) = self.prev_chunk() {
+            if let Some(i) = s.rfind('\n') {
+                self.offset = i + 1;
+                self.pos += count_chars(&s[..i + 1]);
+                return;
+            }
+        }
+    }
+
+    /// Move the cursor to the start of the previous line. If the cursor is on the first line, it
+    /// is moved to the start of the rope and this returns false.
+    pub fn prev_line(&mut self) -> bool {
+        self.line_start();
+        if self.prev_char().is_none() { return false; }
+        self.line_start();
+        true
+    }
+}
+
+impl<'a> Debug for RopeCursor<'a> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("RopeCursor")
+            .field("pos", &self.pos)
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::prelude::*;
+    use crate::JumpRope;
+
+    #[test]
+    fn cursor_random_edits() {
+        let mut rng = SmallRng::seed_from_u64(5);
+        let start = "κό𝕐𝕆😘σμε hi there\nthis is long enough to span several nodes\n";
+
+        for _i in 0..20 {
+            let mut rope = JumpRope::from(start);
+            let mut expected: Vec<char> = start.chars().collect();
+            let mut cursor = rope.cursor_at(rng.gen_range(0..=expected.len()));
+            let mut pos = cursor.pos();
+
+            for _j in 0..100 {
+                match rng.gen_range(0..4) {
+                    0 => {
+                        let content = ["x", "𝕐𝕆", "ab\n", "κόσμε"][rng.gen_range(0..4)];
+                        cursor.insert(content);
+                        expected.splice(pos..pos, content.chars());
+                        pos += content.chars().count();
+                    }
+                    1 => {
+                        let n = rng.gen_range(0..=(expected.len() - pos).min(5));
+                        cursor.delete_forward(n);
+                        expected.drain(pos..pos + n);
+                    }
+                    2 => {
+                        let n = rng.gen_range(0..=pos.min(5));
+                        cursor.delete_backward(n);
+                        expected.drain(pos - n..pos);
+                        pos -= n;
+                    }
+                    _ => {
+                        let new_pos = rng.gen_range(pos.saturating_sub(10)..=(pos + 10).min(expected.len()));
+                        cursor.move_by(new_pos as isize - pos as isize);
+                        pos = new_pos;
+                    }
+                }
+                assert_eq!(cursor.pos(), pos);
+                assert_eq!(cursor.len_chars(), expected.len());
+                #[cfg(feature = "wchar_conversion")]
+                assert_eq!(cursor.cursor.wchar_pos(), expected[..pos].iter().map(|c| c.len_utf16()).sum::<usize>());
+            }
+
+            rope.check();
+            assert_eq!(rope, expected.iter().collect::<String>());
+        }
+    }
+
+    #[test]
+    fn cursor_long_moves() {
+        let content = "κό𝕐𝕆😘σμε\n".repeat(50);
+        let mut rope = JumpRope::from(content.as_str());
+        let len = rope.len_chars();
+
+        let mut cursor = rope.cursor_at(len);
+        cursor.move_to(1);
+        cursor.insert("<");
+        cursor.move_to(len);
+        cursor.move_by(-(len as isize) + 3);
+        assert_eq!(cursor.pos(), 3);
+        cursor.move_by(len as isize - 5);
+        cursor.delete_backward(len - 10);
+        cursor.insert(">");
+        assert_eq!(cursor.pos(), 9);
+        cursor.move_to(0);
+        cursor.delete_forward(1);
+
+        let mut expected: Vec<char> = content.chars().collect();
+        expected.insert(1, '<');
+        expected.splice(8..len - 2, Some('>'));
+        expected.remove(0);
+        rope.check();
+        assert_eq!(rope, expected.iter().collect::<String>());
+    }
+
+    #[test]
+    fn read_cursor_random_walk() {
+        let mut rng = SmallRng::seed_from_u64(8);
+        let mut rope = JumpRope::from("κό𝕐𝕆😘σμε hi there\nthis is long enough\n\nto span several nodes\n".repeat(3));
+        // Leave some gaps in the nodes' gap buffers.
+        rope.insert(10, "xyz");
+        rope.remove(40..42);
+        let expected: Vec<char> = rope.chars().collect();
+
+        let mut cursor = rope.read_cursor_at(0);
+        let mut pos = 0;
+        for _i in 0..2000 {
+            match rng.gen_range(0..6) {
+                0 => {
+                    assert_eq!(cursor.next_char(), expected.get(pos).copied());
+                    pos = (pos + 1).min(expected.len());
+                }
+                1 => {
+                    assert_eq!(cursor.prev_char(), pos.checked_sub(1).map(|p| expected[p]));
+                    pos = pos.saturating_sub(1);
+                }
+                2 => {
+                    pos = rng.gen_range(pos.saturating_sub(30)..=(pos + 30).min(expected.len()));
+                    cursor.move_to(pos);
+                }
+                3 => {
+                    let s = cursor.next_chunk().unwrap_or("");
+                    let n = s.chars().count();
+                    assert_eq!(s.chars().collect::<Vec<_>>(), &expected[pos..pos + n]);
+                    assert!(n > 0 || pos == expected.len());
+                    pos += n;
+                }
+                4 => {
+                    let s = cursor.prev_chunk().unwrap_or("");
+                    let n = s.chars().count();
+                    assert_eq!(s.chars().collect::<Vec<_>>(), &expected[pos - n..pos]);
+                    assert!(n > 0 || pos == 0);
+                    pos -= n;
+                }
+                _ => {
+                    let found = cursor.next_line();
+                    let next = expected[pos..].iter().position(|c| *c == '\n');
+                    assert_eq!(found, next.is_some());
+                    pos = next.map_or(expected.len(), |i| pos + i + 1);
+                }
+            }
+            assert_eq!(cursor.pos(), pos);
+            let chunk_start = cursor.chunk_start();
+            let chunk: Vec<char> = cursor.chunk().chars().collect();
+            assert_eq!(chunk, &expected[chunk_start..chunk_start + chunk.len()]);
+        }
+    }
+
+    #[test]
+    fn read_cursor_backwards() {
+        let mut rope = JumpRope::from("κό𝕐𝕆😘σμε\n".repeat(40));
+        // Leave gaps (and empty halves) in some nodes.
+        for pos in [5, 77, 200, 201, 350] {
+            rope.insert(pos, "x");
+            rope.remove(pos + 1..pos + 3);
+        }
+        let content = rope.to_string();
+
+        // Read the whole rope backwards, then forwards part way and backwards again.
+        let mut cursor = rope.read_cursor_at(rope.len_chars());
+        let mut chunks = vec![];
+        while let Some(s) = cursor.prev_chunk() { chunks.push(s); }
+        assert_eq!(chunks.iter().rev().copied().collect::<String>(), content);
+        assert_eq!(cursor.pos(), 0);
+        assert_eq!(cursor.prev_char(), None);
+
+        for _i in 0..100 { cursor.next_char(); }
+        let mut s = String::new();
+        while let Some(c) = cursor.prev_char() { s.insert(0, c); }
+        assert_eq!(s, content.chars().take(100).collect::<String>());
+
+        assert_eq!(JumpRope::new().read_cursor_at(0).prev_chunk(), None);
+    }
+
+    #[test]
+    fn read_cursor_lines() {
+        let rope = JumpRope::from("aa\nbbb\n\ncc");
+        let mut cursor = rope.read_cursor_at(5);
+        cursor.line_start();
+        assert_eq!(cursor.pos(), 3);
+        assert!(cursor.prev_line());
+        assert_eq!(cursor.pos(), 0);
+        assert!(!cursor.prev_line());
+
+        cursor.move_to(8);
+        assert!(cursor.prev_line());
+        assert_eq!(cursor.pos(), 7);
+        assert!(cursor.prev_line());
+        assert_eq!(cursor.pos(), 3);
+        assert!(cursor.next_line());
+        assert!(cursor.next_line());
+        assert_eq!(cursor.pos(), 8);
+        assert!(!cursor.next_line());
+        assert_eq!(cursor.pos(), 10);
+    }
+}
@@ -0,0 +1,474 @@
+//! Computing a minimal set of edits which turns one document into another.
+//!
+//! The common prefix and suffix of the two documents are skipped first, by comparing their chunks
+//! directly. The remaining middle section is diffed by unicode character using Myers' O(ND)
+//! algorithm. If that would take too many edits, the middle is diffed line by line instead, and
+//! each changed group of lines is then diffed by character.
+
+use std::ops::Range;
+use crate::fast_str_tools::count_chars;
+use crate::JumpRope;
+
+/// A single edit to a document, in the same shape as `crdt_testdata::TestPatch`. The patch deletes
+/// `.1` unicode characters at char position `.0`, then inserts `.2` at the same position.
+///
+/// Lists of patches are applied in order, so the position of each patch names a position in the
+/// document after all the previous patches have been applied.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Patch(pub usize, pub usize, pub String);
+
+/// Myers' algorithm takes O(ND) time and O(D^2) memory, where D is the number of characters (or
+/// lines) which changed. This is the limit on D used by [`JumpRope::diff`].
+pub const DEFAULT_DIFF_LIMIT: usize = 1000;
+
+fn is_continuation(b: u8) -> bool {
+    b & 0xc0 == 0x80
+}
+
+/// Find the common prefix of two documents, each passed as a list of chunks. Returns the length of
+/// the prefix in (bytes, chars).
+fn common_prefix(a: &[&str], b: &[&str]) -> (usize, usize) {
+    let mut a_iter = a.iter();
+    let mut b_iter = b.iter();
+    let mut ra = "";
+    let mut rb = "";
+    let mut bytes = 0;
+    let mut chars = 0;
+
+    loop {
+        if ra.is_empty() {
+            match a_iter.next() { Some(s) => { ra = s; continue; } None => break }
+        }
+        if rb.is_empty() {
+            match b_iter.next() { Some(s) => { rb = s; continue; } None => break }
+        }
+
+        let n = ra.len().min(rb.len());
+        let (xa, xb) = (&ra.as_bytes()[..n], &rb.as_bytes()[..n]);
+        if let Some(mut i) = xa.iter().zip(xb).position(|(x, y)| x != y) {
+            // Only count whole characters.
+            while is_continuation(xa[i]) { i -= 1; }
+            bytes += i;
+            chars += count_chars(&ra[..i]);
+            break;
+        }
+
+        bytes += n;
+        chars += count_chars(&ra[..n]);
+        ra = &ra[n..];
+        rb = &rb[n..];
+    }
+
+    (bytes, chars)
+}
+
+/// Find the common suffix of two documents, up to limit bytes long. Returns the length of the
+/// suffix in (bytes, chars).
+fn common_suffix(a: &[&str], b: &[&str], limit: usize) -> (usize, usize) {
+    let mut a_iter = a.iter().rev();
+    let mut b_iter = b.iter().rev();
+    let mut ra = "";
+    let mut rb = "";
+    let mut bytes = 0;
+    let mut chars = 0;
+
+    while bytes < limit {
+        if ra.is_empty() {
+            match a_iter.next() { Some(s) => { ra = s; continue; } None => break }
+        }
+        if rb.is_empty() {
+            match b_iter.next() { Some(s) => { rb = s; continue; } None => break }
+        }
+
+        let n = ra.len().min(rb.len()).min(limit - bytes);
+        let (xa, xb) = (&ra.as_bytes()[ra.len() - n..], &rb.as_bytes()[rb.len() - n..]);
+        if let Some(mut i) = xa.iter().rev().zip(xb.iter().rev()).position(|(x, y)| x != y) {
+            while i > 0 && is_continuation(xa[n - i]) { i -= 1; }
+            bytes += i;
+            chars += count_chars(&ra[ra.len() - i..]);
+            break;
+        }
+
+        bytes += n;
+        chars += count_chars(&ra[ra.len() - n..]);
+        ra = &ra[..ra.len() - n];
+        rb = &rb[..rb.len() - n];
+    }
+
+    (bytes, chars)
+}
+
+/// Iterate over the byte range start..end of a chunked document.
+fn chunks_in_range<'a>(chunks: &'a [&'a str], start: usize, end: usize)
+    -> impl Iterator<Item = &'a str> + Clone + 'a
+{
+    let mut offset = 0;
+    chunks.iter().filter_map(move |s| {
+        let (s_start, s_end) = (offset, offset + s.len());
+        offset = s_end;
+        if s_end <= start || s_start >= end { return None; }
+        Some(&s[start.saturating_sub(s_start)..end.min(s_end) - s_start])
+    })
+}
+
+/// Run Myers' diff over a and b. Returns the edits as a list of (x, y, is_insert), where each edit
+/// deletes a[x] or inserts b[y]. Returns None if more than max_cost edits are needed.
+fn myers<T: PartialEq>(a: &[T], b: &[T], max_cost: usize) -> Option<Vec<(usize, usize, bool)>> {
+    let n = a.len() as isize;
+    let m = b.len() as isize;
+    let max = (a.len() + b.len()).min(max_cost) as isize;
+
+    // v[idx(k)] is the furthest x reached along diagonal k. trace[d] is a copy of v[-d..=d]
+    // before step d, which is needed to walk back through the edits.
+    let mut v = vec![0isize; 2 * max as usize + 3];
+    let idx = |k: isize| (k + max + 1) as usize;
+    let mut trace: Vec<Vec<isize>> = Vec::new();
+
+    let mut end_d = None;
+    'outer: for d in 0..=max {
+        trace.push(v[idx(-d)..=idx(d)].to_vec());
+
+        for k in (-d..=d).step_by(2) {
+            let mut x = if k == -d || (k != d && v[idx(k - 1)] < v[idx(k + 1)]) {
+                v[idx(k + 1)]
+            } else {
+                v[idx(k - 1)] + 1
+            };
+            let mut y = x - k;
+            while x < n && y < m && a[x as usize] == b[y as usize] {
+                x += 1;
+                y += 1;
+            }
+            v[idx(k)] = x;
+            if x >= n && y >= m {
+                end_d = Some(d);
+                break 'outer;
+            }
+        }
+    }
+
+    let end_d = end_d?;
+
+    let mut edits = Vec::with_capacity(end_d as usize);
+    let (mut x, mut y) = (n, m);
+    for d in (1..=end_d).rev() {
+        let v = &trace[d as usize];
+        let get = |k: isize| v[(k + d) as usize];
+        let k = x - y;
+        let prev_k = if k == -d || (k != d && get(k - 1) < get(k + 1)) { k + 1 } else { k - 1 };
+        let prev_x = get(prev_k);
+        let prev_y = prev_x - prev_k;
+
+        if prev_k == k + 1 {
+            edits.push((prev_x as usize, prev_y as usize, true));
+        } else {
+            edits.push((prev_x as usize, prev_y as usize, false));
+        }
+        x = prev_x;
+        y = prev_y;
+    }
+
+    edits.reverse();
+    Some(edits)
+}
+
+/// Group adjacent edits from [`myers`] into hunks. Each hunk replaces a[xs] with b[ys].
+fn hunks(edits: Vec<(usize, usize, bool)>) -> Vec<(Range<usize>, Range<usize>)> {
+    let mut hunks: Vec<(Range<usize>, Range<usize>)> = Vec::new();
+    for (x, y, is_insert) in edits {
+        match hunks.last_mut() {
+            Some((xs, ys)) if xs.end == x && ys.end == y => {
+                if is_insert { ys.end += 1; } else { xs.end += 1; }
+            }
+            _ => hunks.push(if is_insert { (x..x, y..y + 1) } else { (x..x + 1, y..y) }),
+        }
+    }
+    hunks
+}
+
+/// Diff a and b by unicode character. The patches are offset by `offset` chars. Returns None if
+/// more than max_cost edits are needed.
+fn diff_chars(a: &str, b: &str, offset: usize, max_cost: usize) -> Option<Vec<Patch>> {
+    // Every character in the length difference needs its own edit. If that's already over the
+    // limit, don't bother collecting the characters.
+    if count_chars(a).abs_diff(count_chars(b)) > max_cost { return None; }
+
+    let a: Vec<char> = a.chars().collect();
+    let b: Vec<char> = b.chars().collect();
+    let edits = myers(&a, &b, max_cost)?;
+
+    // Because the patches are applied in order, the position of each patch is its position in b.
+    Some(hunks(edits).into_iter().map(|(xs, ys)| {
+        Patch(offset + ys.start, xs.len(), b[ys].iter().collect())
+    }).collect())
+}
+
+/// Diff a and b line by line, then diff each changed group of lines with [`diff_chars`]. Groups
+/// which are still too different are replaced in one patch. Returns None if more than max_cost
+/// lines need to be inserted or deleted.
+fn diff_lines(a: &str, b: &str, offset: usize, max_cost: usize) -> Option<Vec<Patch>> {
+    let a_lines: Vec<&str> = a.split_inclusive('\n').collect();
+    let b_lines: Vec<&str> = b.split_inclusive('\n').collect();
+    if a_lines.len().abs_diff(b_lines.len()) > max_cost { return None; }
+    let edits = myers(&a_lines, &b_lines, max_cost)?;
+
+    let mut patches = Vec::new();
+    // Lines consumed so far, and the matching byte offsets into a and b and char offset into b.
+    let (mut x, mut y) = (0, 0);
+    let (mut a_byte, mut b_byte, mut b_char) = (0, 0, 0);
+    for (xs, ys) in hunks(edits) {
+        a_byte += a_lines[x..xs.start].iter().map(|l| l.len()).sum::<usize>();
+        for line in &b_lines[y..ys.start] {
+            b_byte += line.len();
+            b_char += count_chars(line);
+        }
+
+        let a_len: usize = a_lines[xs.clone()].iter().map(|l| l.len()).sum();
+        let b_len: usize = b_lines[ys.clone()].iter().map(|l| l.len()).sum();
+        let a_hunk = &a[a_byte..a_byte + a_len];
+        let b_hunk = &b[b_byte..b_byte + b_len];
+        match diff_chars(a_hunk, b_hunk, offset + b_char, max_cost) {
+            Some(hunk_patches) => patches.extend(hunk_patches),
+            None => patches.push(Patch(offset + b_char, count_chars(a_hunk), b_hunk.into())),
+        }
+
+        a_byte += a_len;
+        b_byte += b_len;
+        b_char += count_chars(b_hunk);
+        x = xs.end;
+        y = ys.end;
+    }
+
+    Some(patches)
+}
+
+/// Diff two documents, each passed as a list of chunks.
+pub(crate) fn diff_chunks(a: &[&str], b: &[&str], max_cost: usize) -> Vec<Patch> {
+    let a_bytes: usize = a.iter().map(|s| s.len()).sum();
+    let b_bytes: usize = b.iter().map(|s| s.len()).sum();
+
+    let (prefix_bytes, prefix_chars) = common_prefix(a, b);
+    let limit = a_bytes.min(b_bytes) - prefix_bytes;
+    let (suffix_bytes, _) = common_suffix(a, b, limit);
+
+    if prefix_bytes + suffix_bytes == a_bytes && prefix_bytes + suffix_bytes == b_bytes {
+        return Vec::new();
+    }
+
+    let a_mid: String = chunks_in_range(a, prefix_bytes, a_bytes - suffix_bytes).collect();
+    let b_mid: String = chunks_in_range(b, prefix_bytes, b_bytes - suffix_bytes).collect();
+
+    diff_chars(&a_mid, &b_mid, prefix_chars, max_cost)
+        .or_else(|| diff_lines(&a_mid, &b_mid, prefix_chars, max_cost))
+        .unwrap_or_else(|| vec![Patch(prefix_chars, count_chars(&a_mid), b_mid)])
+}
+
+impl JumpRope {
+    /// Compute a minimal list of patches which turns this rope into `other`.
+    ///
+    /// The patches should be applied in order (eg with [`replace`](Self::replace)). Each patch is
+    /// positioned relative to the document after the previous patches have been applied.
+    ///
+    /// The common prefix and suffix of the two ropes are skipped efficiently. The rest is diffed
+    /// character by character. If turning the changed region into `other` would take more than
+    /// [`DEFAULT_DIFF_LIMIT`] single character insertions and deletions, the changed region is
+    /// diffed line by line instead, and each changed group of lines is diffed by character (or
+    /// replaced in one patch if that is still over the limit). If even the line diff is over the
+    /// limit, the whole changed region is replaced using a single patch.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use jumprope::*;
+    /// let a = JumpRope::from("Hello world!");
+    /// let b = JumpRope::from("Hello cruel world?");
+    /// let patches = a.diff(&b);
+    /// assert_eq!(patches, vec![
+    ///     Patch(6, 0, "cruel ".into()),
+    ///     Patch(17, 1, "?".into()),
+    /// ]);
+    ///
+    /// let mut rope = a.clone();
+    /// for Patch(pos, del_len, ins_content) in patches {
+    ///     rope.replace(pos..pos + del_len, &ins_content);
+    /// }
+    /// assert_eq!(rope, b);
+    /// ```
+    pub fn diff(&self, other: &JumpRope) -> Vec<Patch> {
+        self.diff_with_limit(other, DEFAULT_DIFF_LIMIT)
+    }
+
+    /// Compute a list of patches which turns this rope into `other`, like [`diff`](Self::diff),
+    /// but with a custom limit on the number of edits.
+    ///
+    /// Diffing takes O((N + M) * max_cost) time and O(max_cost^2) memory in the worst case, where N
+    /// and M are the lengths of the changed regions. Raising the limit finds smaller patches for
+    /// large changes, at the cost of more work.
+    pub fn diff_with_limit(&self, other: &JumpRope, max_cost: usize) -> Vec<Patch> {
+        let a: Vec<&str> = self.substrings().collect();
+        let b: Vec<&str> = other.substrings().collect();
+        diff_chunks(&a, &b, max_cost)
+    }
+
+    fn apply_patches(&mut self, patches: Vec<Patch>) {
+        for Patch(pos, del_len, ins_content) in patches {
+            self.replace(pos..pos + del_len, &ins_content);
+        }
+    }
+
+    /// Replace the contents of the rope with `content`, only editing the regions which differ.
+    ///
+    /// Unlike replacing the whole rope, the unchanged parts of the document are left alone. Anchors
+    /// in unchanged text keep their positions, and observers are only notified about the changed
+    /// regions. See [`diff`](Self::diff) for details on how the changes are found.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use jumprope::*;
+    /// let mut rope = JumpRope::from("fn main() {}");
+    /// let anchor = rope.create_anchor(7, Bias::Left); // Before the "("
+    /// rope.set_content("fn  main() { }");
+    /// assert_eq!(rope, "fn  main() { }");
+    /// assert_eq!(rope.anchor_pos(anchor), Some(8));
+    /// ```
+    pub fn set_content(&mut self, content: &str) {
+        let a: Vec<&str> = self.substrings().collect();
+        let patches = diff_chunks(&a, &[content], DEFAULT_DIFF_LIMIT);
+        self.apply_patches(patches);
+    }
+
+    /// Replace the contents of the rope with the contents of `other`, only editing the regions
+    /// which differ. See [`set_content`](Self::set_content).
+    pub fn set_content_from_rope(&mut self, other: &JumpRope) {
+        let patches = self.diff(other);
+        self.apply_patches(patches);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::prelude::*;
+    use crate::{Bias, JumpRope};
+    use super::*;
+
+    fn apply(rope: &mut JumpRope, patches: &[Patch]) {
+        for Patch(pos, del_len, ins_content) in patches {
+            rope.replace(*pos..*pos + *del_len, ins_content);
+        }
+    }
+
+    fn check_diff(a: &JumpRope, b: &JumpRope) -> Vec<Patch> {
+        let patches = a.diff(b);
+        let mut result = a.clone();
+        apply(&mut result, &patches);
+        assert_eq!(result, *b);
+        patches
+    }
+
+    #[test]
+    fn diff_simple() {
+        let diff = |a: &str, b: &str| check_diff(&JumpRope::from(a), &JumpRope::from(b));
+
+        assert_eq!(diff("", ""), vec![]);
+        assert_eq!(diff("hi there", "hi there"), vec![]);
+        assert_eq!(diff("", "hi"), vec![Patch(0, 0, "hi".into())]);
+        assert_eq!(diff("hi", ""), vec![Patch(0, 2, "".into())]);
+        assert_eq!(diff("aaa", "aaaa"), vec![Patch(3, 0, "a".into())]);
+        assert_eq!(diff("κόσμε", "κόxσμε"), vec![Patch(2, 0, "x".into())]);
+
+        // Characters which share leading bytes shouldn't be split.
+        assert_eq!(diff("a𝕐b", "a𝕆b"), vec![Patch(1, 1, "𝕆".into())]);
+        assert_eq!(diff("a😘", "a😙"), vec![Patch(1, 1, "😙".into())]);
+
+        // kitten -> sitting needs 5 single character edits.
+        let patches = diff("kitten", "sitting");
+        assert_eq!(patches.iter().map(|p| p.1 + count_chars(&p.2)).sum::<usize>(), 5);
+    }
+
+    #[test]
+    fn set_content_keeps_anchors() {
+        let mut rope = JumpRope::from("aaa bbb ccc");
+        let anchors = [0, 3, 8, 11].map(|pos| rope.create_anchor(pos, Bias::Left));
+
+        rope.set_content("aaa BbB ccc");
+        rope.check();
+        assert_eq!(rope, "aaa BbB ccc");
+        assert_eq!(anchors.map(|a| rope.anchor_pos(a).unwrap()), [0, 3, 8, 11]);
+
+        rope.set_content_from_rope(&JumpRope::from("ccc"));
+        assert_eq!(rope, "ccc");
+        assert_eq!(rope.anchor_pos(anchors[3]), Some(3));
+    }
+
+    #[test]
+    fn diff_large_change() {
+        let a = JumpRope::from("x".repeat(5000));
+        let b = JumpRope::from(format!("a{}b", "y".repeat(5000)));
+        assert_eq!(check_diff(&a, &b).len(), 1);
+
+        // Inserting or deleting lots of text is replaced without running the diff.
+        let a = JumpRope::from("κόσμε");
+        let b = JumpRope::from(format!("κό{}σμε", "𝕐".repeat(1001)));
+        assert_eq!(check_diff(&a, &b), vec![Patch(2, 0, "𝕐".repeat(1001))]);
+        assert_eq!(check_diff(&b, &a), vec![Patch(2, 1001, "".into())]);
+
+        // But right at the limit, the edits are still found.
+        let b = JumpRope::from(format!("κό{}σμεx", "𝕐".repeat(999)));
+        assert_eq!(check_diff(&a, &b), vec![Patch(2, 0, "𝕐".repeat(999)), Patch(1004, 0, "x".into())]);
+    }
+
+    #[test]
+    fn diff_line_fallback() {
+        let a: String = (0..40).map(|i| format!("line {i:02}\n")).collect();
+        let b = a.replace("line 05", "LINE 05").replace("line 30", "line 3x");
+        let (a, b) = (JumpRope::from(a), JumpRope::from(b));
+
+        let diff = |max_cost: usize| {
+            let patches = a.diff_with_limit(&b, max_cost);
+            let mut result = a.clone();
+            apply(&mut result, &patches);
+            assert_eq!(result, b);
+            patches
+        };
+
+        // With enough budget, the characters are diffed directly.
+        assert_eq!(diff(100), vec![Patch(40, 4, "LINE".into()), Patch(246, 1, "x".into())]);
+
+        // Otherwise only the changed lines are diffed, and the line which is still too expensive
+        // is replaced whole.
+        assert_eq!(diff(5), vec![Patch(40, 8, "LINE 05\n".into()), Patch(246, 1, "x".into())]);
+
+        // If even the line diff is over the limit, the middle is replaced in one patch.
+        assert_eq!(diff(1), vec![Patch(40, 207, b.slice_substrings(40..247).collect())]);
+    }
+
+    #[test]
+    fn diff_random_edits() {
+        let mut rng = SmallRng::seed_from_u64(11);
+        let base = "κό𝕐𝕆😘σμε hi there\nthis is long enough to span several nodes\n".repeat(4);
+
+        for _i in 0..200 {
+            let a = JumpRope::from(base.as_str());
+            let mut b = a.clone();
+            let mut cost = 0;
+            for _j in 0..rng.gen_range(0..5) {
+                let len = b.len_chars();
+                let start = rng.gen_range(0..=len);
+                let end = rng.gen_range(start..=len.min(start + 10));
+                let content = ["", "x", "𝕐𝕆", "ab\n", "😙"][rng.gen_range(0..5)];
+                b.replace(start..end, content);
+                cost += end - start + count_chars(content);
+            }
+
+            let patches = check_diff(&a, &b);
+            assert!(patches.iter().map(|p| p.1 + count_chars(&p.2)).sum::<usize>() <= cost);
+            check_diff(&b, &a);
+
+            let mut c = a.clone();
+            c.set_content(&b.to_string());
+            c.check();
+            assert_eq!(c, b);
+        }
+    }
+}
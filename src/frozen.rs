@@ -0,0 +1,632 @@
+//! Cheap, immutable snapshots of a rope.
+//!
+//! JumpRope's skip list can't share nodes between ropes. Nodes only point forward, so sharing a
+//! node would mean sharing everything after it too, and any edit modifies the nodes before the
+//! edit location at every level of the list. So snapshots are stored in a separate persistent
+//! structure instead: a treap (a randomly balanced binary tree) of text chunks, where each node is
+//! reference counted. Editing the tree copies the O(log n) nodes on the path to the edit and shares
+//! everything else with older snapshots.
+//!
+//! The rope's content is *not* shared with the skip list. While snapshots are in use, the rope
+//! keeps a mirror of its content in a tree like this, and updates it as it's edited. Taking
+//! another snapshot is then just a reference count increment. Once all of the snapshots have been
+//! dropped, the mirror is dropped too (after a few more edits, so ropes which are snapshotted
+//! after every edit don't need to rebuild it each time).
+
+use std::fmt::{Debug, Display, Formatter};
+use std::ops::Range;
+use std::str::Chars;
+use std::sync::Arc;
+use rand::prelude::*;
+use crate::fast_str_tools::{char_to_byte_idx, count_chars};
+use crate::observers::EditEvent;
+use crate::{JumpRope, JumpRopeBuf};
+
+/// Chunks are split when they get longer than this many bytes.
+const CHUNK_SIZE: usize = 512;
+
+type Tree = Option<Arc<Node>>;
+
+#[derive(Clone)]
+struct Node {
+    chunk: Arc<str>,
+    chunk_chars: usize,
+    /// Random priority. Each node has a higher priority than its children.
+    priority: u32,
+    left: Tree,
+    right: Tree,
+
+    /// The number of chars in this subtree.
+    chars: usize,
+    /// The number of bytes in this subtree.
+    bytes: usize,
+}
+
+fn chars(t: &Tree) -> usize {
+    t.as_ref().map_or(0, |n| n.chars)
+}
+
+fn bytes(t: &Tree) -> usize {
+    t.as_ref().map_or(0, |n| n.bytes)
+}
+
+impl Node {
+    fn new(chunk: &str, priority: u32) -> Self {
+        let chunk_chars = count_chars(chunk);
+        Node {
+            chunk: chunk.into(),
+            chunk_chars,
+            priority,
+            left: None,
+            right: None,
+            chars: chunk_chars,
+            bytes: chunk.len(),
+        }
+    }
+
+    /// Recalculate the totals for this subtree after its children have changed.
+    fn update(&mut self) {
+        self.chars = chars(&self.left) + self.chunk_chars + chars(&self.right);
+        self.bytes = bytes(&self.left) + self.chunk.len() + bytes(&self.right);
+    }
+}
+
+// All of these functions take trees by value. Nodes which aren't shared with any snapshot are
+// modified in place, and shared nodes are copied (via Arc::make_mut) before they're modified.
+
+/// Join two trees, with all of the content of a before all of the content of b.
+fn merge(a: Tree, b: Tree) -> Tree {
+    match (a, b) {
+        (None, t) | (t, None) => t,
+        (Some(mut a), Some(mut b)) => Some(if a.priority > b.priority {
+            let n = Arc::make_mut(&mut a);
+            n.right = merge(n.right.take(), Some(b));
+            n.update();
+            a
+        } else {
+            let n = Arc::make_mut(&mut b);
+            n.left = merge(Some(a), n.left.take());
+            n.update();
+            b
+        }),
+    }
+}
+
+/// Split a tree into its first char_pos characters and the rest.
+fn split(t: Tree, char_pos: usize) -> (Tree, Tree) {
+    let mut t = match t {
+        Some(t) => t,
+        None => return (None, None),
+    };
+
+    let n = Arc::make_mut(&mut t);
+    let left_chars = chars(&n.left);
+    if char_pos <= left_chars {
+        let (a, b) = split(n.left.take(), char_pos);
+        n.left = b;
+        n.update();
+        (a, Some(t))
+    } else if char_pos >= left_chars + n.chunk_chars {
+        let (a, b) = split(n.right.take(), char_pos - left_chars - n.chunk_chars);
+        n.right = a;
+        n.update();
+        (Some(t), b)
+    } else {
+        // The split point is inside this node's chunk. The end of the chunk moves into a new node
+        // with the same priority, which takes this node's right subtree.
+        let offset = char_pos - left_chars;
+        let split_byte = char_to_byte_idx(&n.chunk, offset);
+        let mut end = Node::new(&n.chunk[split_byte..], n.priority);
+        end.right = n.right.take();
+        end.update();
+
+        n.chunk = n.chunk[..split_byte].into();
+        n.chunk_chars = offset;
+        n.update();
+        (Some(t), Some(Arc::new(end)))
+    }
+}
+
+/// Remove the last chunk from a tree.
+fn pop_last(mut t: Arc<Node>) -> (Tree, Arc<str>) {
+    let n = Arc::make_mut(&mut t);
+    match n.right.take() {
+        Some(right) => {
+            let (right, chunk) = pop_last(right);
+            n.right = right;
+            n.update();
+            (Some(t), chunk)
+        }
+        None => (n.left.take(), n.chunk.clone()),
+    }
+}
+
+/// Remove the first chunk from a tree.
+fn pop_first(mut t: Arc<Node>) -> (Tree, Arc<str>) {
+    let n = Arc::make_mut(&mut t);
+    match n.left.take() {
+        Some(left) => {
+            let (left, chunk) = pop_first(left);
+            n.left = left;
+            n.update();
+            (Some(t), chunk)
+        }
+        None => (n.right.take(), n.chunk.clone()),
+    }
+}
+
+/// Build a tree containing text, split into chunks of (about) equal size.
+fn build(text: &str, rng: &mut SmallRng) -> Tree {
+    let num_chunks = text.len().div_ceil(CHUNK_SIZE);
+    if num_chunks == 0 { return None; }
+    let target = text.len().div_ceil(num_chunks);
+
+    let mut tree = None;
+    let mut rest = text;
+    while !rest.is_empty() {
+        let mut len = target.min(rest.len());
+        while !rest.is_char_boundary(len) { len += 1; }
+        let (chunk, r) = rest.split_at(len);
+        tree = merge(tree, Some(Arc::new(Node::new(chunk, rng.gen()))));
+        rest = r;
+    }
+    tree
+}
+
+/// An immutable snapshot of a rope's content, created with [`JumpRope::snapshot`].
+///
+/// Snapshots share their content with each other, so cloning a snapshot is *O(1)*. They're
+/// `Send` and `Sync`, so they can be handed to other threads while the rope continues to be
+/// edited.
+///
+/// # Example
+///
+/// ```
+/// # use jumprope::*;
+/// let mut rope = JumpRope::from("Hello world!");
+/// let snapshot = rope.snapshot();
+/// rope.replace(6..11, "everyone");
+///
+/// assert_eq!(snapshot, "Hello world!");
+/// assert_eq!(rope, "Hello everyone!");
+/// assert_eq!(rope.snapshot(), "Hello everyone!");
+/// ```
+#[derive(Clone, Default)]
+pub struct FrozenRope {
+    root: Tree,
+    /// Shared by every snapshot taken from the same mirror, so the rope can tell when they've all
+    /// been dropped.
+    users: Arc<()>,
+}
+
+impl FrozenRope {
+    /// Return the length of the snapshot in unicode characters.
+    pub fn len_chars(&self) -> usize {
+        chars(&self.root)
+    }
+
+    /// Get the number of bytes used for the UTF8 representation of the snapshot.
+    pub fn len_bytes(&self) -> usize {
+        bytes(&self.root)
+    }
+
+    /// Returns `true` if the snapshot contains no text.
+    pub fn is_empty(&self) -> bool {
+        self.root.is_none()
+    }
+
+    /// Iterate over the snapshot's content in chunks.
+    pub fn substrings(&self) -> FrozenSubstrings<'_> {
+        self.slice_substrings(0..self.len_chars())
+    }
+
+    /// Iterate over a range of the snapshot's content in chunks. The range is specified in
+    /// unicode characters. Finding the start of the range takes *O(log n)* time.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the range is reversed or extends past the end of the snapshot.
+    pub fn slice_substrings(&self, range: Range<usize>) -> FrozenSubstrings<'_> {
+        assert!(range.start <= range.end, "Slice range is reversed");
+        assert!(range.end <= self.len_chars(), "Range extends past the end of the snapshot");
+
+        // Find the node containing the start of the range. Every node we pass on the way down to
+        // the left comes after it, and gets visited later.
+        let mut stack = vec![];
+        let mut t = self.root.as_deref();
+        let mut skip = range.start;
+        while let Some(n) = t {
+            let left_chars = chars(&n.left);
+            if skip < left_chars {
+                stack.push(n);
+                t = n.left.as_deref();
+            } else if skip < left_chars + n.chunk_chars {
+                stack.push(n);
+                skip -= left_chars;
+                break;
+            } else {
+                skip -= left_chars + n.chunk_chars;
+                t = n.right.as_deref();
+            }
+        }
+
+        FrozenSubstrings { stack, skip, remaining: range.end - range.start }
+    }
+
+    /// Iterate over the characters in the snapshot.
+    pub fn chars(&self) -> FrozenChars<'_> {
+        self.slice_chars(0..self.len_chars())
+    }
+
+    /// Iterate over a range of characters in the snapshot.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the range is reversed or extends past the end of the snapshot.
+    pub fn slice_chars(&self, range: Range<usize>) -> FrozenChars<'_> {
+        FrozenChars {
+            substrings: self.slice_substrings(range),
+            current: "".chars(),
+        }
+    }
+
+    /// Copy the snapshot's content into a new (editable) rope. This is *O(n)*.
+    pub fn to_rope(&self) -> JumpRope {
+        let mut rope = JumpRope::new();
+        rope.extend(self.substrings());
+        rope
+    }
+
+    fn eq_str(&self, mut other: &str) -> bool {
+        if self.len_bytes() != other.len() { return false; }
+
+        for s in self.substrings() {
+            let (start, rem) = other.split_at(s.len());
+            if start != s { return false; }
+            other = rem;
+        }
+
+        true
+    }
+
+    /// Replace the characters in range with inserted.
+    fn edit(&mut self, range: Range<usize>, inserted: &str, rng: &mut SmallRng) {
+        let (before, rest) = split(self.root.take(), range.start);
+        let (_, after) = split(rest, range.end - range.start);
+
+        // The chunks on either side of the edit are rebuilt along with the inserted text, so
+        // edits don't leave lots of tiny chunks behind.
+        let (before, prev) = before.map_or((None, None), |t| {
+            let (t, chunk) = pop_last(t);
+            (t, Some(chunk))
+        });
+        let (after, next) = after.map_or((None, None), |t| {
+            let (t, chunk) = pop_first(t);
+            (t, Some(chunk))
+        });
+
+        let mut text = String::new();
+        text.extend(prev.as_deref());
+        text.push_str(inserted);
+        text.extend(next.as_deref());
+
+        self.root = merge(merge(before, build(&text, rng)), after);
+    }
+}
+
+impl Debug for FrozenRope {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.debug_list()
+            .entries(self.substrings())
+            .finish()
+    }
+}
+
+impl Display for FrozenRope {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        for s in self.substrings() {
+            f.write_str(s)?;
+        }
+        Ok(())
+    }
+}
+
+impl<T: AsRef<str>> PartialEq<T> for FrozenRope {
+    fn eq(&self, other: &T) -> bool {
+        self.eq_str(other.as_ref())
+    }
+}
+
+impl PartialEq<str> for FrozenRope {
+    fn eq(&self, other: &str) -> bool {
+        self.eq_str(other)
+    }
+}
+
+impl PartialEq<FrozenRope> for FrozenRope {
+    fn eq(&self, other: &FrozenRope) -> bool {
+        if self.len_bytes() != other.len_bytes() { return false; }
+        self.chars().eq(other.chars())
+    }
+}
+
+impl Eq for FrozenRope {}
+
+/// An iterator over the chunks of content in a [`FrozenRope`].
+#[derive(Clone)]
+pub struct FrozenSubstrings<'a> {
+    /// The nodes which still need to be visited, with the next node on top.
+    stack: Vec<&'a Node>,
+    /// The number of chars to skip at the start of the next chunk.
+    skip: usize,
+    /// The number of chars left to yield.
+    remaining: usize,
+}
+
+impl<'a> Iterator for FrozenSubstrings<'a> {
+    type Item = &'a str;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.remaining == 0 { return None; }
+
+        let n = self.stack.pop()?;
+
+        // Queue up the nodes after this one.
+        let mut t = n.right.as_deref();
+        while let Some(next) = t {
+            self.stack.push(next);
+            t = next.left.as_deref();
+        }
+
+        let mut s: &'a str = &n.chunk;
+        let mut len = n.chunk_chars;
+        if self.skip > 0 {
+            s = &s[char_to_byte_idx(s, self.skip)..];
+            len -= self.skip;
+            self.skip = 0;
+        }
+        if len > self.remaining {
+            s = &s[..char_to_byte_idx(s, self.remaining)];
+            len = self.remaining;
+        }
+        self.remaining -= len;
+        Some(s)
+    }
+}
+
+/// An iterator over the characters in a [`FrozenRope`].
+#[derive(Clone)]
+pub struct FrozenChars<'a> {
+    substrings: FrozenSubstrings<'a>,
+    current: Chars<'a>,
+}
+
+impl<'a> Iterator for FrozenChars<'a> {
+    type Item = char;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            if let Some(c) = self.current.next() { return Some(c); }
+            self.current = self.substrings.next()?.chars();
+        }
+    }
+}
+
+/// Build a tree containing the content of a rope. The rope's nodes are smaller than our chunks,
+/// so they get joined together.
+fn build_from_rope(rope: &JumpRope, rng: &mut SmallRng) -> Tree {
+    let mut root = None;
+    let mut text = String::with_capacity(CHUNK_SIZE);
+    for s in rope.substrings() {
+        if text.len() + s.len() > CHUNK_SIZE {
+            root = merge(root, build(&text, rng));
+            text.clear();
+        }
+        text.push_str(s);
+    }
+    merge(root, build(&text, rng))
+}
+
+/// A frozen copy of a rope, which is kept up to date as the rope is edited while snapshots of it
+/// are in use. This is fed the rope's edits along with its observers.
+pub(crate) struct SnapshotMirror {
+    rope: FrozenRope,
+    rng: SmallRng,
+    /// The number of edits since a snapshot of the mirror was last in use.
+    idle_edits: usize,
+}
+
+impl SnapshotMirror {
+    fn new(rope: &JumpRope, mut rng: SmallRng) -> Self {
+        let root = build_from_rope(rope, &mut rng);
+        Self {
+            rope: FrozenRope { root, users: Arc::default() },
+            rng,
+            idle_edits: 0,
+        }
+    }
+
+    /// Called before each edit. Returns false if the mirror should be dropped instead of being
+    /// updated, because no snapshots have used it for long enough that keeping it up to date
+    /// costs more than rebuilding it for the next snapshot would.
+    pub(crate) fn keep(&mut self) -> bool {
+        if Arc::strong_count(&self.rope.users) > 1 {
+            self.idle_edits = 0;
+        } else {
+            self.idle_edits += 1;
+        }
+        self.idle_edits <= self.rope.len_bytes() / CHUNK_SIZE
+    }
+
+    pub(crate) fn apply(&mut self, event: &EditEvent) {
+        self.rope.edit(event.range.clone(), event.inserted, &mut self.rng);
+    }
+
+    /// Add the content of other onto the end of the mirror. If other has a mirror of its own, its
+    /// tree is shared in *O(log n)* time. Otherwise other's content is copied.
+    pub(crate) fn append(&mut self, other: &JumpRope) {
+        let tree = match other.observers.as_ref().and_then(|o| o.mirror.as_ref()) {
+            Some(mirror) => mirror.rope.root.clone(),
+            None => build_from_rope(other, &mut self.rng),
+        };
+        self.rope.root = merge(self.rope.root.take(), tree);
+    }
+}
+
+impl JumpRope {
+    /// Take an immutable snapshot of the rope's current content. See [`FrozenRope`].
+    ///
+    /// Snapshots don't share memory with the rope itself. While any snapshot is in use, the rope
+    /// keeps a second, copy-on-write copy of its content (which snapshots share with each other),
+    /// and updates it as the rope is edited at a cost of *O(log n)* per edit. So:
+    ///
+    /// - If the rope has no copy, taking a snapshot builds one in *O(n)* time.
+    /// - Otherwise taking a snapshot is *O(1)*.
+    /// - While the copy exists, the rope uses about twice as much memory for its content.
+    ///
+    /// The copy is dropped a little while after the last snapshot has been dropped. Snapshots
+    /// aren't affected by later edits to the rope.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use jumprope::*;
+    /// let mut rope = JumpRope::from("abc");
+    /// let a = rope.snapshot();
+    /// rope.insert(3, "def");
+    /// let b = rope.snapshot();
+    ///
+    /// std::thread::spawn(move || {
+    ///     assert_eq!(a, "abc");
+    ///     assert_eq!(b, "abcdef");
+    /// }).join().unwrap();
+    /// ```
+    pub fn snapshot(&mut self) -> FrozenRope {
+        if let Some(mirror) = self.observers.as_mut().and_then(|o| o.mirror.as_mut()) {
+            mirror.idle_edits = 0;
+            return mirror.rope.clone();
+        }
+
+        let rng = SmallRng::seed_from_u64(self.rng.gen());
+        let mirror = SnapshotMirror::new(self, rng);
+        let snapshot = mirror.rope.clone();
+        self.observers.get_or_insert_with(Default::default).mirror = Some(mirror);
+        snapshot
+    }
+}
+
+impl JumpRopeBuf {
+    /// Take an immutable snapshot of the rope's current content, after flushing any buffered
+    /// edits. See [`JumpRope::snapshot`].
+    pub fn snapshot(&mut self) -> FrozenRope {
+        self.as_mut().snapshot()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::prelude::*;
+    use crate::JumpRope;
+    use super::CHUNK_SIZE;
+
+    #[test]
+    fn snapshots_keep_their_content() {
+        let mut rng = SmallRng::seed_from_u64(5);
+        let mut rope = JumpRope::from("κό𝕐𝕆😘σμε");
+        let mut snapshots = vec![(rope.snapshot(), rope.to_string())];
+
+        for i in 0..300 {
+            let len = rope.len_chars();
+            let pos = rng.gen_range(0..=len);
+            let del_len = rng.gen_range(0..=(len - pos).min(20));
+            let content = "x𝕐\n".repeat(rng.gen_range(0..30));
+            rope.replace(pos..pos + del_len, &content);
+
+            if i % 10 == 0 {
+                snapshots.push((rope.snapshot(), rope.to_string()));
+            }
+        }
+
+        for (snapshot, expected) in &snapshots {
+            assert_eq!(snapshot, expected);
+            assert_eq!(snapshot.len_chars(), expected.chars().count());
+            assert_eq!(snapshot.len_bytes(), expected.len());
+            assert_eq!(snapshot.to_rope(), expected);
+        }
+        assert_eq!(rope.snapshot(), rope.to_string());
+    }
+
+    #[test]
+    fn snapshot_other_edits() {
+        let mut rope = JumpRope::from("hello ");
+        let empty = JumpRope::new().snapshot();
+        assert!(empty.is_empty());
+        assert_eq!(empty, "");
+
+        let before = rope.snapshot();
+        rope.append(JumpRope::from("world, this is long enough for several nodes"));
+        let rest = rope.split_off(11);
+        rope.remove(0..1);
+        assert_eq!(before, "hello ");
+        assert_eq!(rope.snapshot(), "ello world");
+        assert_eq!(rest, ", this is long enough for several nodes");
+    }
+
+    fn has_mirror(rope: &JumpRope) -> bool {
+        rope.observers.as_ref().is_some_and(|o| o.mirror.is_some())
+    }
+
+    #[test]
+    fn mirror_dropped_when_unused() {
+        let mut rope = JumpRope::from("hi there");
+        let snapshot = rope.snapshot();
+        rope.insert(0, "a");
+        assert!(has_mirror(&rope));
+
+        drop(snapshot);
+        rope.insert(0, "b");
+        assert!(!has_mirror(&rope));
+        assert_eq!(rope.snapshot(), "bahi there");
+
+        // Bigger ropes keep the mirror for a few more edits, in case they're snapshotted again.
+        let mut rope = JumpRope::from("x".repeat(CHUNK_SIZE * 4));
+        drop(rope.snapshot());
+        for _i in 0..4 {
+            rope.insert(0, "y");
+            assert!(has_mirror(&rope));
+        }
+        rope.insert(0, "y");
+        assert!(!has_mirror(&rope));
+    }
+
+    #[test]
+    fn append_snapshots() {
+        let mut rope = JumpRope::from("hello ");
+        let before = rope.snapshot();
+
+        // other's mirror is shared with the rope.
+        let mut other = JumpRope::from("world, this is long enough for several nodes");
+        let other_snapshot = other.snapshot();
+        rope.append(other);
+        rope.append(JumpRope::from("!"));
+        rope.check();
+
+        assert_eq!(rope.snapshot(), "hello world, this is long enough for several nodes!");
+        assert_eq!(before, "hello ");
+        assert_eq!(other_snapshot, "world, this is long enough for several nodes");
+    }
+
+    #[test]
+    fn slice_snapshots() {
+        let s = "κό𝕐𝕆😘σμε ".repeat(200);
+        let mut rope = JumpRope::from(s.as_str());
+        let snapshot = rope.snapshot();
+
+        let len = s.chars().count();
+        for range in [0..0, 0..len, 3..4, 100..1500, len..len] {
+            let expected: String = s.chars().skip(range.start).take(range.len()).collect();
+            assert_eq!(snapshot.slice_substrings(range.clone()).collect::<String>(), expected);
+            assert_eq!(snapshot.slice_chars(range).collect::<String>(), expected);
+        }
+    }
+}
@@ -1,6 +1,6 @@
 use crate::fast_str_tools::*;
 #[cfg(feature = "line_conversion")]
-use crate::utils::count_lines;
+use crate::utils::{count_lines, str_lines_to_bytes};
 use crate::utils::str_chars_to_bytes_rev;
 
 #[derive(Debug, Clone, Eq)]
@@ -281,6 +281,21 @@ impl<const LEN: usize> GapBuffer<LEN> {
         }
     }
 
+    /// Append the characters in char_pos..char_pos+char_len to out.
+    pub(crate) fn push_chars_to(&self, out: &mut String, char_pos: usize, char_len: usize) {
+        let start = self.count_bytes(char_pos);
+        let end = self.count_bytes(char_pos + char_len);
+        let s1 = self.start_as_str();
+        let s2 = self.end_as_str();
+
+        if start < s1.len() {
+            out.push_str(&s1[start..end.min(s1.len())]);
+        }
+        if end > s1.len() {
+            out.push_str(&s2[start.max(s1.len()) - s1.len()..end - s1.len()]);
+        }
+    }
+
     pub fn count_bytes(&self, char_pos: usize) -> usize {
         if self.all_ascii { return char_pos; }
 
@@ -297,6 +312,22 @@ impl<const LEN: usize> GapBuffer<LEN> {
         }
     }
 
+    /// Returns the number of chars in the first `byte_pos` bytes of the buffer, or `None` if
+    /// `byte_pos` lands inside a UTF-8 encoded character.
+    pub(crate) fn count_chars_in_bytes(&self, byte_pos: usize) -> Option<usize> {
+        if self.all_ascii { return Some(byte_pos); }
+
+        let gap_bytes = self.gap_start_bytes as usize;
+        let (s, offset, base_chars) = if byte_pos <= gap_bytes {
+            (self.start_as_str(), byte_pos, 0)
+        } else {
+            (self.end_as_str(), byte_pos - gap_bytes, self.gap_start_chars as usize)
+        };
+
+        if !s.is_char_boundary(offset) { return None; }
+        Some(base_chars + self.count_internal_chars(&s[..offset]))
+    }
+
     /// Calculate & return the number of surrogate pairs in `[0..char_pos]`
     #[cfg(feature = "wchar_conversion")]
     pub(crate) fn count_chars_in_wchars(&self, wchar_pos: usize) -> usize {
@@ -345,6 +376,43 @@ impl<const LEN: usize> GapBuffer<LEN> {
         }
     }
 
+    /// Calculate & return the number of newlines in `[0..char_pos]`
+    #[cfg(feature = "line_conversion")]
+    pub(crate) fn count_lines_before(&self, char_pos: usize) -> usize {
+        let gap_chars = self.gap_start_chars as usize;
+        #[allow(clippy::comparison_chain)]
+        if char_pos == gap_chars {
+            self.gap_start_lines as usize
+        } else if char_pos < gap_chars {
+            if self.gap_start_lines == 0 { 0 }
+            else {
+                let s = self.start_as_str();
+                count_lines(&s[..self.int_str_get_byte_offset(s, char_pos)])
+            }
+        } else {
+            let s = self.end_as_str();
+            let bytes = self.int_str_get_byte_offset(s, char_pos - gap_chars);
+            self.gap_start_lines as usize + count_lines(&s[..bytes])
+        }
+    }
+
+    /// Returns the char offset of the start of the line following the `lines`th newline in the
+    /// buffer. The buffer must contain at least that many newlines.
+    #[cfg(feature = "line_conversion")]
+    pub(crate) fn count_chars_in_lines(&self, lines: usize) -> usize {
+        if lines == 0 { return 0; }
+
+        let gap_lines = self.gap_start_lines as usize;
+        if lines <= gap_lines {
+            let s = self.start_as_str();
+            self.count_internal_chars(&s[..str_lines_to_bytes(s, lines)])
+        } else {
+            let s = self.end_as_str();
+            let bytes = str_lines_to_bytes(s, lines - gap_lines);
+            self.gap_start_chars as usize + self.count_internal_chars(&s[..bytes])
+        }
+    }
+
     /// Take the remaining contents in the gap buffer. Mark them as deleted, but return them.
     /// This will leave those items non-zero, but that doesn't matter.
     pub fn take_rest(&mut self) -> &str {
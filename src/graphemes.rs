@@ -0,0 +1,285 @@
+use std::borrow::Cow;
+use std::ops::Range;
+use unicode_segmentation::{GraphemeCursor, GraphemeIncomplete};
+use crate::iter::SubstringsInRange;
+use crate::JumpRope;
+
+/// Iterator over the (extended) grapheme clusters in a rope, or a range within a rope.
+///
+/// Grapheme clusters are borrowed from the rope where possible. Grapheme clusters which span
+/// multiple chunks in the rope are copied into an owned string.
+pub struct Graphemes<'a> {
+    rope: &'a JumpRope,
+    chunks: SubstringsInRange<'a>,
+    /// The chunk being read, and its byte offset from the start of the range.
+    chunk: &'a str,
+    chunk_start: usize,
+    /// The byte offset of the next grapheme cluster from the start of the range.
+    pos: usize,
+    /// The length of the range in bytes.
+    len_bytes: usize,
+    /// The byte offset of the start of the range in the rope.
+    start_bytes: usize,
+}
+
+impl<'a> Iterator for Graphemes<'a> {
+    type Item = Cow<'a, str>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let start = self.pos;
+        if start >= self.len_bytes { return None; }
+
+        // Each grapheme cluster gets a new cursor. Reusing one cursor for the whole range gives the
+        // wrong boundaries for runs of regional indicators which span chunks. (Cursors don't
+        // allocate, so this is cheap.)
+        let mut cursor = GraphemeCursor::new(start, self.len_bytes, true);
+
+        // The start of the grapheme cluster, if it spans multiple chunks.
+        let mut spanned = String::new();
+
+        let end = loop {
+            match cursor.next_boundary(self.chunk, self.chunk_start) {
+                Ok(Some(b)) => break b,
+                Ok(None) => break self.len_bytes,
+                Err(GraphemeIncomplete::NextChunk) => {
+                    let chunk_end = self.chunk_start + self.chunk.len();
+                    if start < chunk_end {
+                        spanned.push_str(&self.chunk[start.saturating_sub(self.chunk_start)..]);
+                    }
+                    self.chunk = self.chunks.next().unwrap_or("");
+                    self.chunk_start = chunk_end;
+                }
+                Err(GraphemeIncomplete::PreContext(b)) => {
+                    // This is rare, so the context is looked up directly in the rope.
+                    let (ctx, ctx_start) = self.rope.chunk_at_byte(self.start_bytes + b - 1);
+                    let from = ctx_start.max(self.start_bytes);
+                    let ctx = &ctx[from - ctx_start..self.start_bytes + b - ctx_start];
+                    cursor.provide_context(ctx, from - self.start_bytes);
+                }
+                Err(e) => panic!("Unexpected grapheme cursor error: {:?}", e),
+            }
+        };
+
+        self.pos = end;
+        let local_end = end - self.chunk_start;
+        Some(if spanned.is_empty() {
+            Cow::Borrowed(&self.chunk[start - self.chunk_start..local_end])
+        } else {
+            spanned.push_str(&self.chunk[..local_end]);
+            Cow::Owned(spanned)
+        })
+    }
+}
+
+/// These methods are only available if the `graphemes` feature is enabled.
+///
+/// Grapheme clusters are found using the extended grapheme cluster rules from the
+/// [unicode-segmentation](https://crates.io/crates/unicode-segmentation) crate. All positions
+/// are still specified in unicode characters.
+#[cfg_attr(doc_cfg, doc(cfg(feature = "graphemes")))]
+impl JumpRope {
+    /// Find the char position of the next grapheme cluster boundary after `char_pos`. If
+    /// `char_pos` is at the end of the rope, this returns the length of the rope.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `char_pos` is past the end of the rope.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use jumprope::*;
+    /// let rope = JumpRope::from("a🐻‍❄️b");
+    /// assert_eq!(rope.next_grapheme_boundary(0), 1);
+    /// assert_eq!(rope.next_grapheme_boundary(1), 5); // Skip the whole polar bear
+    /// assert_eq!(rope.next_grapheme_boundary(2), 5);
+    /// ```
+    pub fn next_grapheme_boundary(&self, char_pos: usize) -> usize {
+        let len_bytes = self.len_bytes();
+        let byte_pos = self.char_to_byte(char_pos);
+        if byte_pos == len_bytes { return char_pos; }
+
+        let mut gc = GraphemeCursor::new(byte_pos, len_bytes, true);
+        let (mut chunk, mut chunk_start) = self.chunk_at_byte(byte_pos);
+
+        loop {
+            match gc.next_boundary(chunk, chunk_start) {
+                Ok(None) => return self.len_chars(),
+                Ok(Some(b)) => return self.byte_to_char(b).unwrap(),
+                Err(GraphemeIncomplete::NextChunk) => {
+                    (chunk, chunk_start) = self.chunk_at_byte(chunk_start + chunk.len());
+                }
+                Err(GraphemeIncomplete::PreContext(b)) => {
+                    let (ctx, ctx_start) = self.chunk_at_byte(b - 1);
+                    gc.provide_context(&ctx[..b - ctx_start], ctx_start);
+                }
+                Err(e) => panic!("Unexpected grapheme cursor error: {:?}", e),
+            }
+        }
+    }
+
+    /// Find the char position of the grapheme cluster boundary before `char_pos`. If `char_pos`
+    /// is 0, this returns 0.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `char_pos` is past the end of the rope.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use jumprope::*;
+    /// let rope = JumpRope::from("a🐻‍❄️b");
+    /// assert_eq!(rope.prev_grapheme_boundary(6), 5);
+    /// assert_eq!(rope.prev_grapheme_boundary(5), 1); // Skip the whole polar bear
+    /// assert_eq!(rope.prev_grapheme_boundary(3), 1);
+    /// ```
+    pub fn prev_grapheme_boundary(&self, char_pos: usize) -> usize {
+        let byte_pos = self.char_to_byte(char_pos);
+        if byte_pos == 0 { return 0; }
+
+        let mut gc = GraphemeCursor::new(byte_pos, self.len_bytes(), true);
+        // Start with the chunk ending at (or containing) byte_pos.
+        let (mut chunk, mut chunk_start) = self.chunk_at_byte(byte_pos - 1);
+
+        loop {
+            match gc.prev_boundary(chunk, chunk_start) {
+                Ok(None) => return 0,
+                Ok(Some(b)) => return self.byte_to_char(b).unwrap(),
+                Err(GraphemeIncomplete::PrevChunk) => {
+                    (chunk, chunk_start) = self.chunk_at_byte(chunk_start - 1);
+                }
+                Err(GraphemeIncomplete::PreContext(b)) => {
+                    let (ctx, ctx_start) = self.chunk_at_byte(b - 1);
+                    gc.provide_context(&ctx[..b - ctx_start], ctx_start);
+                }
+                Err(e) => panic!("Unexpected grapheme cursor error: {:?}", e),
+            }
+        }
+    }
+
+    /// Iterate over the grapheme clusters in the rope.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use jumprope::*;
+    /// let rope = JumpRope::from("a🐻‍❄️b");
+    /// let graphemes = rope.graphemes().map(|g| g.to_string()).collect::<Vec<_>>();
+    /// assert_eq!(graphemes, vec!["a", "🐻‍❄️", "b"]);
+    /// ```
+    pub fn graphemes(&self) -> Graphemes<'_> {
+        self.slice_graphemes(0..self.len_chars())
+    }
+
+    /// Iterate over the grapheme clusters within the specified unicode character range of the
+    /// rope. The ends of the range are treated as grapheme boundaries.
+    ///
+    /// This runs in *O(log n)* time to find the start of the range, then *O(1)* amortized time per
+    /// grapheme cluster.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the range is reversed or extends past the end of the rope.
+    pub fn slice_graphemes(&self, range: Range<usize>) -> Graphemes<'_> {
+        assert!(range.start <= range.end, "Range is reversed");
+        assert!(range.end <= self.len_chars(), "Range extends past the end of the rope");
+
+        let start_bytes = self.char_to_byte(range.start);
+        let len_bytes = self.char_to_byte(range.end) - start_bytes;
+        let mut chunks = self.slice_substrings(range);
+        Graphemes {
+            rope: self,
+            chunk: chunks.next().unwrap_or(""),
+            chunks,
+            chunk_start: 0,
+            pos: 0,
+            len_bytes,
+            start_bytes,
+        }
+    }
+
+    /// Delete the grapheme cluster immediately before `char_pos`, like pressing backspace in a
+    /// text editor. Returns the char position of the start of the deleted grapheme cluster (ie,
+    /// where the cursor should end up).
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use jumprope::*;
+    /// let mut rope = JumpRope::from("a🐻‍❄️b");
+    /// assert_eq!(rope.remove_prev_grapheme(5), 1);
+    /// assert_eq!(rope, "ab");
+    /// ```
+    pub fn remove_prev_grapheme(&mut self, char_pos: usize) -> usize {
+        let start = self.prev_grapheme_boundary(char_pos);
+        self.remove(start..char_pos);
+        start
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use unicode_segmentation::UnicodeSegmentation;
+    use crate::JumpRope;
+    use crate::test_utils::rope_with_gaps;
+
+    fn check_graphemes(s: &str) {
+        let rope = rope_with_gaps(s, 3);
+        assert_eq!(rope, s);
+
+        let expected = s.graphemes(true).collect::<Vec<_>>();
+        let actual = rope.graphemes().map(|g| g.to_string()).collect::<Vec<_>>();
+        assert_eq!(actual, expected);
+
+        // Slices treat their ends as boundaries, even in the middle of a grapheme cluster.
+        let len = rope.len_chars();
+        for (start, end) in [(0, 0), (1.min(len), len), (len / 2, len), (len / 3, len / 2), (len, len)] {
+            let sub = s.chars().skip(start).take(end - start).collect::<String>();
+            let expected = sub.graphemes(true).collect::<Vec<_>>();
+            assert_eq!(rope.slice_graphemes(start..end).collect::<Vec<_>>(), expected);
+        }
+
+        // Check boundaries at every position.
+        let mut boundaries = vec![0];
+        for g in &expected {
+            boundaries.push(boundaries.last().unwrap() + g.chars().count());
+        }
+        for pos in 0..=rope.len_chars() {
+            let next = *boundaries.iter().find(|&&b| b > pos).unwrap_or(&rope.len_chars());
+            let prev = *boundaries.iter().rev().find(|&&b| b < pos).unwrap_or(&0);
+            assert_eq!(rope.next_grapheme_boundary(pos), next);
+            assert_eq!(rope.prev_grapheme_boundary(pos), prev);
+        }
+    }
+
+    #[test]
+    fn grapheme_boundaries() {
+        check_graphemes("");
+        check_graphemes("hi there");
+        check_graphemes("🐻‍❄️🐻‍❄️ polar bears 🐻‍❄️ and flags 🇦🇺🇳🇿 e\u{301}\u{302} \r\n done");
+        // Long runs of regional indicators need context from earlier chunks to pair them up.
+        check_graphemes(&format!("x{}y", "🇦".repeat(9)));
+    }
+
+    #[test]
+    #[should_panic]
+    #[allow(clippy::reversed_empty_ranges)]
+    fn reversed_grapheme_range_panics() {
+        JumpRope::from("abc").slice_graphemes(2..1);
+    }
+
+    #[test]
+    fn delete_graphemes() {
+        let mut rope = JumpRope::from("🐻‍❄️🇦🇺🇳🇿x\r\n");
+        let mut pos = rope.len_chars();
+        let mut deleted = 0;
+        while pos > 0 {
+            pos = rope.remove_prev_grapheme(pos);
+            rope.check();
+            deleted += 1;
+        }
+        assert_eq!(deleted, 5);
+        assert!(rope.is_empty());
+    }
+}
@@ -0,0 +1,362 @@
+//! This module provides a wrapper around [`JumpRope`] which records every edit, so edits can be
+//! undone and redone.
+//!
+//! Consecutive typing (and consecutive deletes) are merged together into a single undo step, in
+//! a similar way to how [`JumpRopeBuf`](crate::JumpRopeBuf) merges adjacent edits. Edits can also
+//! be grouped explicitly using transactions.
+
+use std::fmt::{Debug, Display, Formatter};
+use std::ops::Range;
+use crate::fast_str_tools::count_chars;
+use crate::JumpRope;
+
+/// A single recorded edit, which replaced `deleted` at `pos` with `inserted`.
+#[derive(Debug, Clone)]
+struct HistoryEdit {
+    pos: usize,
+    deleted: String,
+    inserted: String,
+}
+
+impl HistoryEdit {
+    /// Try to merge the next edit onto the end of this one. This mirrors
+    /// `BufferedOp::try_append`, and only merges runs of typing or runs of deletes.
+    fn try_append(&mut self, next: &HistoryEdit) -> Result<(), ()> {
+        if self.deleted.is_empty() && next.deleted.is_empty() {
+            // Typing forwards.
+            if next.pos == self.pos + count_chars(&self.inserted) {
+                self.inserted.push_str(&next.inserted);
+                return Ok(());
+            }
+        } else if self.inserted.is_empty() && next.inserted.is_empty() {
+            if next.pos == self.pos {
+                // Deleting forwards (with the delete key).
+                self.deleted.push_str(&next.deleted);
+                return Ok(());
+            } else if next.pos + count_chars(&next.deleted) == self.pos {
+                // Backspacing.
+                self.deleted.insert_str(0, &next.deleted);
+                self.pos = next.pos;
+                return Ok(());
+            }
+        }
+
+        Err(())
+    }
+}
+
+/// A group of edits which are undone and redone together.
+type Transaction = Vec<HistoryEdit>;
+
+/// A wrapper around [`JumpRope`] which records edits so they can be undone and redone.
+///
+/// Edits made through `insert`, `remove` and `replace` are recorded along with any deleted text.
+/// Consecutive typing and deleting is merged into a single undo step. Call
+/// [`break_merge`](Self::break_merge) to stop the next edit from merging (eg when the user moves
+/// their cursor), or group edits explicitly using
+/// [`begin_transaction`](Self::begin_transaction) / [`end_transaction`](Self::end_transaction).
+///
+/// # Example
+///
+/// ```
+/// # use jumprope::*;
+/// let mut doc = JumpRopeHistory::new();
+/// doc.insert(0, "H");
+/// doc.insert(1, "i"); // Merged with the previous insert
+/// doc.break_merge();
+/// doc.insert(2, " there");
+/// assert_eq!(doc.rope(), "Hi there");
+///
+/// doc.undo();
+/// assert_eq!(doc.rope(), "Hi");
+/// doc.undo();
+/// assert_eq!(doc.rope(), "");
+/// doc.redo();
+/// assert_eq!(doc.rope(), "Hi");
+/// ```
+pub struct JumpRopeHistory {
+    rope: JumpRope,
+    undo_stack: Vec<Transaction>,
+    redo_stack: Vec<Transaction>,
+
+    /// The nesting depth of explicit transactions.
+    transaction_depth: usize,
+
+    /// Set if the next edit can be added into the transaction at the top of the undo stack.
+    can_merge: bool,
+}
+
+impl JumpRopeHistory {
+    pub fn with_rope(rope: JumpRope) -> Self {
+        Self {
+            rope,
+            undo_stack: Vec::new(),
+            redo_stack: Vec::new(),
+            transaction_depth: 0,
+            can_merge: false,
+        }
+    }
+
+    pub fn new() -> Self {
+        Self::with_rope(JumpRope::new())
+    }
+
+    /// Get a reference to the contained rope.
+    pub fn rope(&self) -> &JumpRope {
+        &self.rope
+    }
+
+    /// Consume the history, returning the contained rope.
+    pub fn into_inner(self) -> JumpRope {
+        self.rope
+    }
+
+    fn record(&mut self, edit: HistoryEdit) {
+        if edit.deleted.is_empty() && edit.inserted.is_empty() { return; }
+
+        self.redo_stack.clear();
+
+        if self.can_merge {
+            let txn = self.undo_stack.last_mut().unwrap();
+            if let Some(last) = txn.last_mut() {
+                if last.try_append(&edit).is_ok() { return; }
+            }
+
+            // Inside a transaction, every edit is added to the current undo step.
+            if self.transaction_depth > 0 {
+                txn.push(edit);
+                return;
+            }
+        }
+
+        self.undo_stack.push(vec![edit]);
+        self.can_merge = true;
+    }
+
+    /// Insert new content into the rope at the specified unicode character position, recording
+    /// the edit. See [`JumpRope::insert`].
+    pub fn insert(&mut self, pos: usize, content: &str) {
+        self.replace(pos..pos, content);
+    }
+
+    /// Remove the specified range of unicode characters, recording the deleted text. See
+    /// [`JumpRope::remove`].
+    pub fn remove(&mut self, range: Range<usize>) {
+        self.replace(range, "");
+    }
+
+    /// Replace the specified range with new content, recording the edit. See
+    /// [`JumpRope::replace`].
+    pub fn replace(&mut self, range: Range<usize>, content: &str) {
+        let mut deleted = String::new();
+        let pos = self.rope.replace_internal(range, content, Some(&mut deleted));
+        self.record(HistoryEdit {
+            pos,
+            deleted,
+            inserted: content.to_string(),
+        });
+    }
+
+    /// Stop the next edit from being merged into the previous undo step.
+    pub fn break_merge(&mut self) {
+        if self.transaction_depth == 0 {
+            self.can_merge = false;
+        }
+    }
+
+    /// Start a transaction. All edits made until the matching call to
+    /// [`end_transaction`](Self::end_transaction) are undone and redone together.
+    ///
+    /// Transactions can be nested. The edits are grouped until the outermost transaction ends.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use jumprope::*;
+    /// let mut doc = JumpRopeHistory::with_rope(JumpRope::from("a b"));
+    /// doc.begin_transaction();
+    /// doc.insert(0, "(");
+    /// doc.insert(4, ")");
+    /// doc.end_transaction();
+    /// assert_eq!(doc.rope(), "(a b)");
+    ///
+    /// doc.undo();
+    /// assert_eq!(doc.rope(), "a b");
+    /// ```
+    pub fn begin_transaction(&mut self) {
+        if self.transaction_depth == 0 {
+            self.undo_stack.push(Vec::new());
+            self.can_merge = true;
+        }
+        self.transaction_depth += 1;
+    }
+
+    /// End a transaction started with [`begin_transaction`](Self::begin_transaction).
+    ///
+    /// # Panics
+    ///
+    /// Panics if there is no open transaction.
+    pub fn end_transaction(&mut self) {
+        assert!(self.transaction_depth > 0, "end_transaction called without a matching begin_transaction");
+        self.transaction_depth -= 1;
+        if self.transaction_depth == 0 {
+            self.can_merge = false;
+            if self.undo_stack.last().is_some_and(|txn| txn.is_empty()) {
+                self.undo_stack.pop();
+            }
+        }
+    }
+
+    /// Returns true if there are any edits which can be undone.
+    pub fn can_undo(&self) -> bool {
+        self.undo_stack.iter().any(|txn| !txn.is_empty())
+    }
+
+    /// Returns true if there are any undone edits which can be redone.
+    pub fn can_redo(&self) -> bool {
+        !self.redo_stack.is_empty()
+    }
+
+    /// Undo the most recent group of edits. Returns false if there was nothing to undo.
+    ///
+    /// # Panics
+    ///
+    /// Panics if called inside a transaction.
+    pub fn undo(&mut self) -> bool {
+        assert_eq!(self.transaction_depth, 0, "Cannot undo inside a transaction");
+        self.can_merge = false;
+
+        let Some(txn) = self.undo_stack.pop() else { return false; };
+        for edit in txn.iter().rev() {
+            let end = edit.pos + count_chars(&edit.inserted);
+            self.rope.replace(edit.pos..end, &edit.deleted);
+        }
+        self.redo_stack.push(txn);
+        true
+    }
+
+    /// Redo the most recently undone group of edits. Returns false if there was nothing to redo.
+    ///
+    /// # Panics
+    ///
+    /// Panics if called inside a transaction.
+    pub fn redo(&mut self) -> bool {
+        assert_eq!(self.transaction_depth, 0, "Cannot redo inside a transaction");
+        self.can_merge = false;
+
+        let Some(txn) = self.redo_stack.pop() else { return false; };
+        for edit in txn.iter() {
+            let end = edit.pos + count_chars(&edit.deleted);
+            self.rope.replace(edit.pos..end, &edit.inserted);
+        }
+        self.undo_stack.push(txn);
+        true
+    }
+
+    /// Forget all recorded undo and redo history.
+    pub fn clear_history(&mut self) {
+        assert_eq!(self.transaction_depth, 0, "Cannot clear history inside a transaction");
+        self.undo_stack.clear();
+        self.redo_stack.clear();
+        self.can_merge = false;
+    }
+}
+
+impl From<JumpRope> for JumpRopeHistory {
+    fn from(rope: JumpRope) -> Self {
+        Self::with_rope(rope)
+    }
+}
+
+impl Default for JumpRopeHistory {
+    fn default() -> Self {
+        JumpRopeHistory::new()
+    }
+}
+
+impl AsRef<JumpRope> for JumpRopeHistory {
+    fn as_ref(&self) -> &JumpRope {
+        &self.rope
+    }
+}
+
+impl Debug for JumpRopeHistory {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("JumpRopeHistory")
+            .field("rope", &self.rope)
+            .field("undo_stack", &self.undo_stack)
+            .field("redo_stack", &self.redo_stack)
+            .finish()
+    }
+}
+
+impl Display for JumpRopeHistory {
+    fn fmt(&self, f: &mut Formatter) -> std::fmt::Result {
+        Display::fmt(&self.rope, f)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::prelude::*;
+    use crate::{JumpRope, JumpRopeHistory};
+
+    #[test]
+    fn typing_merges() {
+        let mut doc = JumpRopeHistory::new();
+        for (i, c) in "hello".chars().enumerate() {
+            doc.insert(i, c.encode_utf8(&mut [0; 4]));
+        }
+        // Backspace twice.
+        doc.remove(4..5);
+        doc.remove(3..4);
+        assert_eq!(doc.rope(), "hel");
+
+        assert!(doc.undo());
+        assert_eq!(doc.rope(), "hello");
+        assert!(doc.undo());
+        assert_eq!(doc.rope(), "");
+        assert!(!doc.undo());
+
+        assert!(doc.redo());
+        assert!(doc.redo());
+        assert!(!doc.redo());
+        assert_eq!(doc.rope(), "hel");
+
+        // A new edit clears the redo stack.
+        doc.undo();
+        doc.insert(0, "x");
+        assert!(!doc.can_redo());
+    }
+
+    #[test]
+    fn undo_random_edits() {
+        let mut rng = SmallRng::seed_from_u64(7);
+        let mut doc = JumpRopeHistory::with_rope(JumpRope::from("κόσμε"));
+        let mut states = vec![doc.rope().to_string()];
+
+        for _i in 0..200 {
+            let len = doc.rope().len_chars();
+            let start = rng.gen_range(0..=len);
+            let end = rng.gen_range(start..=len.min(start + 3));
+            let content = ["", "x", "𝕐𝕆", "ab\n"][rng.gen_range(0..4)];
+            if start == end && content.is_empty() { continue; }
+
+            doc.replace(start..end, content);
+            doc.break_merge();
+            states.push(doc.rope().to_string());
+        }
+
+        for expected in states.iter().rev().skip(1) {
+            doc.undo();
+            doc.rope().check();
+            assert_eq!(doc.rope(), expected);
+        }
+        assert!(!doc.can_undo());
+
+        for expected in states.iter().skip(1) {
+            doc.redo();
+            assert_eq!(doc.rope(), expected);
+        }
+    }
+}
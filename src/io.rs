@@ -0,0 +1,379 @@
+use std::fmt;
+use std::io;
+use std::io::{BufRead, Read, Write};
+use std::ops::Range;
+use crate::iter::SliceIter;
+use crate::jumprope::MutCursor;
+use crate::JumpRope;
+
+/// A reader over the UTF-8 contents of a rope (or a range within a rope). This implements
+/// [`Read`] and [`BufRead`], handing out the rope's internal chunks directly.
+///
+/// Created with [`JumpRope::reader`] or [`JumpRope::slice_reader`].
+pub struct RopeReader<'a> {
+    chunks: SliceIter<'a>,
+    /// The remainder of the current chunk.
+    current: &'a [u8],
+}
+
+impl<'a> Read for RopeReader<'a> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let src = self.fill_buf()?;
+        let len = src.len().min(buf.len());
+        buf[..len].copy_from_slice(&src[..len]);
+        self.consume(len);
+        Ok(len)
+    }
+}
+
+impl<'a> BufRead for RopeReader<'a> {
+    fn fill_buf(&mut self) -> io::Result<&[u8]> {
+        if self.current.is_empty() {
+            if let Some((s, _)) = self.chunks.next() {
+                self.current = s.as_bytes();
+            }
+        }
+        Ok(self.current)
+    }
+
+    fn consume(&mut self, amt: usize) {
+        self.current = &self.current[amt..];
+    }
+}
+
+/// A writer which inserts text into a rope at an advancing position. Each write is inserted
+/// directly after the previous one, using a single cursor into the rope rather than looking up
+/// the position again for every fragment.
+///
+/// This implements both [`fmt::Write`] (so it can be used with `write!`) and [`io::Write`]. When
+/// using `io::Write`, multi-byte characters may be split across calls to `write`. Incomplete
+/// characters are held back until the rest of their bytes arrive. Writing invalid UTF-8 returns
+/// an error of kind [`io::ErrorKind::InvalidData`].
+///
+/// Created with [`JumpRope::writer`] or [`JumpRope::writer_at`].
+pub struct RopeWriter<'a> {
+    cursor: MutCursor<'a>,
+
+    /// The start of a multi-byte character which hasn't been completely written yet.
+    pending: [u8; 4],
+    pending_len: usize,
+}
+
+fn invalid_utf8() -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, "stream did not contain valid UTF-8")
+}
+
+/// Returns the length of the UTF-8 sequence which starts with the passed byte, or 0 if the byte
+/// can't start a character.
+fn utf8_char_width(first_byte: u8) -> usize {
+    match first_byte {
+        0x00..=0x7f => 1,
+        0xc2..=0xdf => 2,
+        0xe0..=0xef => 3,
+        0xf0..=0xf4 => 4,
+        _ => 0,
+    }
+}
+
+impl<'a> RopeWriter<'a> {
+    fn new(cursor: MutCursor<'a>) -> Self {
+        Self {
+            cursor,
+            pending: [0; 4],
+            pending_len: 0,
+        }
+    }
+
+    fn insert(&mut self, s: &str) {
+        JumpRope::insert_at_cursor(&mut self.cursor, s);
+    }
+
+    /// Consume the writer, checking that no partially written character is left over.
+    ///
+    /// Any incomplete character at the end of the stream is silently dropped if the writer is
+    /// dropped without calling this method.
+    pub fn finish(self) -> io::Result<()> {
+        if self.pending_len > 0 {
+            Err(io::Error::new(io::ErrorKind::UnexpectedEof, "stream ended part way through a UTF-8 character"))
+        } else {
+            Ok(())
+        }
+    }
+}
+
+impl<'a> Write for RopeWriter<'a> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let mut consumed = 0;
+
+        if self.pending_len > 0 {
+            // Finish off the character from the last write.
+            let width = utf8_char_width(self.pending[0]);
+            let take = (width - self.pending_len).min(buf.len());
+            let mut pending = self.pending;
+            let len = self.pending_len + take;
+            pending[self.pending_len..len].copy_from_slice(&buf[..take]);
+
+            // The bytes are checked before anything is changed, so nothing is consumed if the
+            // character turns out to be invalid.
+            match std::str::from_utf8(&pending[..len]) {
+                Ok(s) => {
+                    self.insert(s);
+                    self.pending_len = 0;
+                }
+                Err(e) if e.error_len().is_none() => {
+                    // Still incomplete.
+                    self.pending = pending;
+                    self.pending_len = len;
+                    return Ok(take);
+                }
+                Err(_) => return Err(invalid_utf8()),
+            }
+            consumed = take;
+        }
+
+        match std::str::from_utf8(&buf[consumed..]) {
+            Ok(s) => {
+                self.insert(s);
+                Ok(buf.len())
+            }
+            Err(e) => {
+                let valid_len = e.valid_up_to();
+                let (valid, rest) = buf[consumed..].split_at(valid_len);
+                // Safe because from_utf8 told us this part is valid.
+                self.insert(unsafe { std::str::from_utf8_unchecked(valid) });
+
+                if e.error_len().is_some() {
+                    // Report the bytes we've written so far. The caller will get an error when
+                    // they try to write the invalid data again.
+                    if consumed + valid_len > 0 { Ok(consumed + valid_len) } else { Err(invalid_utf8()) }
+                } else {
+                    // The buffer ends part way through a character. Hold onto it until we get
+                    // the rest.
+                    self.pending[..rest.len()].copy_from_slice(rest);
+                    self.pending_len = rest.len();
+                    Ok(buf.len())
+                }
+            }
+        }
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+impl<'a> fmt::Write for RopeWriter<'a> {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        // Don't insert anything in the middle of a partially written character.
+        if self.pending_len > 0 { return Err(fmt::Error); }
+        self.insert(s);
+        Ok(())
+    }
+}
+
+/// Formatted text is appended to the end of the rope.
+///
+/// # Example
+///
+/// ```
+/// # use jumprope::*;
+/// use std::fmt::Write;
+///
+/// let mut rope = JumpRope::from("x = ");
+/// write!(rope, "{}", 123).unwrap();
+/// assert_eq!(rope, "x = 123");
+/// ```
+impl fmt::Write for JumpRope {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        self.extend(Some(s));
+        Ok(())
+    }
+}
+
+impl JumpRope {
+    /// Create a reader over the UTF-8 contents of the rope. The reader implements [`Read`] and
+    /// [`BufRead`], so it can be used with [`io::copy`] without copying the rope into a string
+    /// first.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use jumprope::*;
+    /// use std::io::Read;
+    ///
+    /// let rope = JumpRope::from("κόσμε");
+    /// let mut s = String::new();
+    /// rope.reader().read_to_string(&mut s).unwrap();
+    /// assert_eq!(s, "κόσμε");
+    /// ```
+    pub fn reader(&self) -> RopeReader<'_> {
+        self.slice_reader(0..self.len_chars())
+    }
+
+    /// Create a reader over the UTF-8 contents of a range of the rope. The range is specified in
+    /// unicode characters.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use jumprope::*;
+    /// use std::io::Read;
+    ///
+    /// let rope = JumpRope::from("κόσμε");
+    /// let mut s = String::new();
+    /// rope.slice_reader(1..3).read_to_string(&mut s).unwrap();
+    /// assert_eq!(s, "όσ");
+    /// ```
+    pub fn slice_reader(&self, range: Range<usize>) -> RopeReader<'_> {
+        RopeReader {
+            chunks: self.slice_substrings_with_len(range),
+            current: &[],
+        }
+    }
+
+    /// Create a writer which appends any UTF-8 data written to it onto the end of the rope.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use jumprope::*;
+    /// use std::io::Write;
+    ///
+    /// let mut rope = JumpRope::from("Hi ");
+    /// let mut writer = rope.writer();
+    /// writer.write_all("κόσμε".as_bytes()).unwrap();
+    /// writer.finish().unwrap();
+    /// assert_eq!(rope, "Hi κόσμε");
+    /// ```
+    pub fn writer(&mut self) -> RopeWriter<'_> {
+        RopeWriter::new(self.mut_cursor_at_end())
+    }
+
+    /// Create a writer which inserts text at the specified unicode character position. Each
+    /// subsequent write is inserted after the text written before it.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `char_pos` is past the end of the rope.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use jumprope::*;
+    /// use std::fmt::Write;
+    ///
+    /// let mut rope = JumpRope::from("fn main() {}");
+    /// let mut writer = rope.writer_at(11);
+    /// for i in 0..3 {
+    ///     write!(writer, " f{}();", i).unwrap();
+    /// }
+    /// writer.write_char(' ').unwrap();
+    /// assert_eq!(rope, "fn main() { f0(); f1(); f2(); }");
+    /// ```
+    pub fn writer_at(&mut self, char_pos: usize) -> RopeWriter<'_> {
+        RopeWriter::new(self.mut_cursor_at_char(char_pos, true))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::{BufRead, Read};
+    use crate::JumpRope;
+
+    const TEXT: &str = "κό𝕐𝕆😘σμε hi there\nthis is long enough to span several nodes\nand lines";
+
+    #[test]
+    fn read_rope() {
+        let rope = JumpRope::from(TEXT);
+
+        let mut s = String::new();
+        rope.reader().read_to_string(&mut s).unwrap();
+        assert_eq!(s, TEXT);
+
+        let lines = rope.reader().lines().collect::<Result<Vec<_>, _>>().unwrap();
+        assert_eq!(lines, TEXT.lines().collect::<Vec<_>>());
+
+        // Read with a tiny buffer.
+        let mut reader = rope.slice_reader(3..20);
+        let mut bytes = vec![];
+        let mut buf = [0; 3];
+        loop {
+            let n = reader.read(&mut buf).unwrap();
+            if n == 0 { break; }
+            bytes.extend_from_slice(&buf[..n]);
+        }
+        assert_eq!(bytes, rope.slice(3..20).to_string().as_bytes());
+    }
+
+    #[test]
+    fn write_rope() {
+        use std::io::Write;
+
+        // Write the text one byte at a time, so multi-byte characters are split across writes.
+        let mut rope = JumpRope::from("abc");
+        let mut writer = rope.writer();
+        for b in TEXT.as_bytes() {
+            assert_eq!(writer.write(std::slice::from_ref(b)).unwrap(), 1);
+        }
+        writer.finish().unwrap();
+        rope.check();
+        assert_eq!(rope, format!("abc{}", TEXT));
+
+        let mut rope = JumpRope::new();
+        std::io::copy(&mut TEXT.as_bytes(), &mut rope.writer()).unwrap();
+        assert_eq!(rope, TEXT);
+    }
+
+    #[test]
+    fn write_invalid_utf8() {
+        use std::io::Write;
+
+        let mut rope = JumpRope::new();
+        let mut writer = rope.writer();
+        assert_eq!(writer.write(b"ab\xffcd").unwrap(), 2);
+        assert!(writer.write(b"\xffcd").is_err());
+        assert!(writer.write_all(b"\xce").is_ok());
+        assert!(writer.finish().is_err());
+        assert_eq!(rope, "ab");
+
+        // Invalid bytes after the start of a character aren't consumed.
+        let mut writer = rope.writer();
+        assert_eq!(writer.write(b"\xce").unwrap(), 1);
+        assert!(writer.write(b"\xffxyz").is_err());
+        assert_eq!(writer.write(b"\xbcxyz").unwrap(), 4);
+        writer.finish().unwrap();
+        assert_eq!(rope, "abμxyz");
+
+        // Including when the character is split across several writes.
+        let mut writer = rope.writer();
+        assert_eq!(writer.write(b"\xf0").unwrap(), 1);
+        assert_eq!(writer.write(b"\x9d").unwrap(), 1);
+        assert!(writer.write(b"a").is_err());
+        writer.write_all(b"\x95\x90").unwrap();
+        writer.finish().unwrap();
+        assert_eq!(rope, "abμxyz𝕐");
+    }
+
+    #[test]
+    fn write_fmt_at_position() {
+        use std::fmt::Write;
+
+        let mut rope = JumpRope::from("start end");
+        let mut writer = rope.writer_at(6);
+        for i in 0..20 {
+            write!(writer, "{} κόσμε ", i).unwrap();
+        }
+        rope.check();
+
+        let mut expected = "start ".to_string();
+        for i in 0..20 {
+            write!(expected, "{} κόσμε ", i).unwrap();
+        }
+        expected.push_str("end");
+        assert_eq!(rope, expected);
+
+        write!(rope, "!").unwrap();
+        expected.push('!');
+        assert_eq!(rope, expected);
+    }
+}
@@ -1,6 +1,7 @@
+use std::iter::Rev;
 use std::ops::Range;
 use crate::jumprope::*;
-use crate::utils::str_chars_to_bytes;
+use crate::utils::{str_chars_to_bytes, str_chars_to_bytes_rev};
 
 /// An iterator over chunks (nodes) in the list.
 pub(crate) struct NodeIter<'a>(Option<&'a Node>);
@@ -18,6 +19,57 @@ impl<'a> Iterator for NodeIter<'a> {
     }
 }
 
+/// The nodes before a node in the rope, used to walk backwards through the rope.
+///
+/// Nodes only link forwards, so the previous nodes are found by searching the skip list for a
+/// position further back, then walking forwards to the current node. Each search goes back twice
+/// as far as the last one, so walking backwards takes *O(1)* amortized time per node (rather than
+/// *O(log n)* time to search for each node).
+#[derive(Clone, Default)]
+pub(crate) struct PrevNodes<'a> {
+    /// The nodes before the current node, ending with the node immediately before it.
+    stack: Vec<&'a Node>,
+    /// How many chars back the last search started from.
+    distance: usize,
+}
+
+impl<'a> PrevNodes<'a> {
+    /// Find the node before `node`, which starts at char position `start`. Returns None if `node`
+    /// is the head of the rope.
+    ///
+    /// This must be called with successive nodes, walking backwards through the rope.
+    pub(crate) fn prev(&mut self, rope: &'a JumpRope, node: &'a Node, start: usize) -> Option<&'a Node> {
+        if std::ptr::eq(node, &rope.head) { return None; }
+
+        if self.stack.is_empty() {
+            self.distance = (self.distance * 2).max(NODE_STR_SIZE);
+            // This finds the first node which ends at or after the position, which is always before
+            // the current node.
+            let mut n = rope.read_cursor_at_char(start.saturating_sub(self.distance), true).node;
+            while !std::ptr::eq(n, node) {
+                self.stack.push(n);
+                n = unsafe { &*n.next_ptr() };
+            }
+        }
+
+        self.stack.pop()
+    }
+
+    /// Record that the walk has moved forwards past `node`, to the next node.
+    pub(crate) fn push(&mut self, node: &'a Node) {
+        // If the stack is empty it'll be refilled by searching anyway.
+        if !self.stack.is_empty() {
+            self.stack.push(node);
+        }
+    }
+
+    /// Forget the previous nodes, after jumping somewhere else in the rope.
+    pub(crate) fn clear(&mut self) {
+        self.stack.clear();
+        self.distance = 0;
+    }
+}
+
 /// A content iterator iterates over the strings in the rope
 pub struct ContentIter<'a> {
     next: Option<&'a Node>,
@@ -33,6 +85,14 @@ impl<'a> ContentIter<'a> {
     pub fn chars(self) -> Chars<'a> {
         self.into()
     }
+
+    pub fn bytes(self) -> Bytes<'a> {
+        self.into()
+    }
+
+    pub fn utf16_units(self) -> Utf16Units<'a> {
+        self.into()
+    }
 }
 
 impl<'a> Iterator for ContentIter<'a> {
@@ -77,17 +137,26 @@ impl<'a, I: Iterator<Item=(&'a str, usize)>> Iterator for Substrings<'a, I> {
     }
 }
 
+impl<'a, I: DoubleEndedIterator<Item=(&'a str, usize)>> DoubleEndedIterator for Substrings<'a, I> {
+    fn next_back(&mut self) -> Option<Self::Item> {
+        self.0.next_back().map(|(s, _)| s)
+    }
+}
+
 /// Iterator over the individual characters in a rope (or rope slice).
 pub struct Chars<'a, I: Iterator<Item=(&'a str, usize)> = ContentIter<'a>> {
     inner: I,
     current: std::str::Chars<'a>,
+    /// The chunk being read from the back, when iterating in reverse.
+    back: std::str::Chars<'a>,
 }
 
 impl<'a, I: Iterator<Item=(&'a str, usize)>> From<I> for Chars<'a, I> {
     fn from(inner: I) -> Self {
         Self {
             inner,
-            current: "".chars()
+            current: "".chars(),
+            back: "".chars(),
         }
     }
 }
@@ -97,7 +166,11 @@ impl<'a, I: Iterator<Item=(&'a str, usize)>> Iterator for Chars<'a, I> {
 
     fn next(&mut self) -> Option<Self::Item> {
         self.current.next().or_else(|| {
-            self.current = self.inner.next()?.0.chars();
+            let Some((s, _)) = self.inner.next() else {
+                // The rest of the content may have been taken from the back.
+                return self.back.next();
+            };
+            self.current = s.chars();
             let next = self.current.next();
             // None of the items returned from our inner iterator should be empty.
             debug_assert!(next.is_some());
@@ -106,17 +179,139 @@ impl<'a, I: Iterator<Item=(&'a str, usize)>> Iterator for Chars<'a, I> {
     }
 }
 
+impl<'a, I: DoubleEndedIterator<Item=(&'a str, usize)>> DoubleEndedIterator for Chars<'a, I> {
+    fn next_back(&mut self) -> Option<Self::Item> {
+        self.back.next_back().or_else(|| {
+            let Some((s, _)) = self.inner.next_back() else {
+                return self.current.next_back();
+            };
+            self.back = s.chars();
+            let next = self.back.next_back();
+            debug_assert!(next.is_some());
+            next
+        })
+    }
+}
+
+/// Iterator over the bytes in the UTF-8 encoding of a rope (or rope slice).
+pub struct Bytes<'a, I: Iterator<Item=(&'a str, usize)> = ContentIter<'a>> {
+    inner: I,
+    current: std::str::Bytes<'a>,
+}
+
+impl<'a, I: Iterator<Item=(&'a str, usize)>> From<I> for Bytes<'a, I> {
+    fn from(inner: I) -> Self {
+        Self {
+            inner,
+            current: "".bytes(),
+        }
+    }
+}
+
+impl<'a, I: Iterator<Item=(&'a str, usize)>> Iterator for Bytes<'a, I> {
+    type Item = u8;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.current.next().or_else(|| {
+            self.current = self.inner.next()?.0.bytes();
+            self.current.next()
+        })
+    }
+}
+
+/// Iterator over the UTF-16 code units (wchars) in a rope (or rope slice). This yields the same
+/// values as [`str::encode_utf16`].
+pub struct Utf16Units<'a, I: Iterator<Item=(&'a str, usize)> = ContentIter<'a>> {
+    inner: I,
+    current: std::str::EncodeUtf16<'a>,
+    /// Code units to skip at the start. This is used when a wchar range starts in the middle of a
+    /// surrogate pair.
+    skip: usize,
+    /// The number of code units left to yield, if limited.
+    take_len: Option<usize>,
+}
+
+impl<'a, I: Iterator<Item=(&'a str, usize)>> From<I> for Utf16Units<'a, I> {
+    fn from(inner: I) -> Self {
+        Self {
+            inner,
+            current: "".encode_utf16(),
+            skip: 0,
+            take_len: None,
+        }
+    }
+}
+
+impl<'a, I: Iterator<Item=(&'a str, usize)>> Iterator for Utf16Units<'a, I> {
+    type Item = u16;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.take_len == Some(0) { return None; }
+
+        loop {
+            if let Some(unit) = self.current.next() {
+                if self.skip > 0 {
+                    self.skip -= 1;
+                    continue;
+                }
+                if let Some(take_len) = self.take_len.as_mut() { *take_len -= 1; }
+                return Some(unit);
+            }
+            self.current = self.inner.next()?.0.encode_utf16();
+        }
+    }
+}
+
+pub type BytesInRange<'a> = Bytes<'a, SliceIter<'a>>;
+pub type Utf16UnitsInRange<'a> = Utf16Units<'a, SliceIter<'a>>;
+
 /// Iterate over a sub-range of the rope.
+///
+/// This also iterates backwards. Nodes only link to the next node, so the chunks at the back are
+/// found in batches by searching further and further back through the rope. Iterating backwards
+/// takes *O(1)* amortized time per chunk, after searching for the end of the range.
 pub struct SliceIter<'a> {
+    rope: &'a JumpRope,
     inner: ContentIter<'a>,
     skip: usize,
     take_len: usize,
+    /// The char position of the end of the remaining content.
+    end: usize,
+    /// The half of a node's gap buffer which was last read from the back, and the char position
+    /// of its start.
+    back: Option<(&'a Node, bool, usize)>,
+    prev_nodes: PrevNodes<'a>,
 }
 
 pub type SubstringsInRange<'a> = Substrings<'a, SliceIter<'a>>;
 pub type CharsInRange<'a> = Chars<'a, SliceIter<'a>>;
 
 impl<'a> SliceIter<'a> {
+    /// Iterate through the characters in range, starting at the passed cursor (which must point
+    /// to range.start).
+    pub(crate) fn from_cursor(rope: &'a JumpRope, cursor: &ReadCursor<'a>, range: Range<usize>) -> Self {
+        let node_gap_start = cursor.node.str.gap_start_chars as usize;
+        let local_pos = cursor.offset_chars;
+
+        let (at_start, skip) = if local_pos >= node_gap_start {
+            (false, local_pos - node_gap_start)
+        } else {
+            (true, local_pos)
+        };
+
+        SliceIter {
+            rope,
+            inner: ContentIter {
+                next: Some(cursor.node), at_start
+            },
+            skip,
+            take_len: range.end - range.start,
+            end: range.end,
+            back: None,
+            prev_nodes: PrevNodes::default(),
+        }
+    }
+
     pub fn substrings(self) -> SubstringsInRange<'a> {
         Substrings(self)
     }
@@ -124,6 +319,14 @@ impl<'a> SliceIter<'a> {
     pub fn chars(self) -> CharsInRange<'a> {
         self.into()
     }
+
+    pub fn bytes(self) -> BytesInRange<'a> {
+        self.into()
+    }
+
+    pub fn utf16_units(self) -> Utf16UnitsInRange<'a> {
+        self.into()
+    }
 }
 
 impl<'a> Iterator for SliceIter<'a> {
@@ -155,6 +358,58 @@ impl<'a> Iterator for SliceIter<'a> {
     }
 }
 
+impl<'a> DoubleEndedIterator for SliceIter<'a> {
+    fn next_back(&mut self) -> Option<Self::Item> {
+        if self.take_len == 0 { return None; }
+
+        let (node, in_end, start) = match self.back {
+            None => {
+                // Find the chunk containing the character before the end.
+                let cursor = self.rope.read_cursor_at_char(self.end, true);
+                let node = cursor.node;
+                let gap_start = node.str.gap_start_chars as usize;
+                let in_end = cursor.offset_chars > gap_start;
+                let local_pos = if in_end { cursor.offset_chars - gap_start } else { cursor.offset_chars };
+                debug_assert!(local_pos > 0);
+                (node, in_end, self.end - local_pos)
+            }
+            Some((mut node, mut in_end, mut start)) => {
+                // The last chunk was read back to its start. Step back to the previous chunk
+                // which isn't empty.
+                loop {
+                    if in_end {
+                        in_end = false;
+                    } else {
+                        node = self.prev_nodes.prev(self.rope, node, start)?;
+                        in_end = true;
+                    }
+                    let len = half_chars(node, in_end);
+                    start -= len;
+                    if len > 0 { break; }
+                }
+                (node, in_end, start)
+            }
+        };
+        self.back = Some((node, in_end, start));
+
+        let chunk = if in_end { node.str.end_as_str() } else { node.str.start_as_str() };
+        let local_pos = self.end - start;
+        let s = &chunk[..str_chars_to_bytes(chunk, local_pos)];
+        let char_len = local_pos.min(self.take_len);
+        let s = &s[s.len() - str_chars_to_bytes_rev(s, char_len)..];
+
+        self.end -= char_len;
+        self.take_len -= char_len;
+        Some((s, char_len))
+    }
+}
+
+/// The number of chars in one half of a node's gap buffer.
+pub(crate) fn half_chars(node: &Node, in_end: bool) -> usize {
+    let gap_start = node.str.gap_start_chars as usize;
+    if in_end { node.num_chars() - gap_start } else { gap_start }
+}
+
 impl JumpRope {
     pub(crate) fn node_iter_at_start(&self) -> NodeIter { NodeIter(Some(&self.head)) }
 
@@ -277,22 +532,7 @@ impl JumpRope {
     /// ```
     pub fn slice_substrings_with_len(&self, range: Range<usize>) -> SliceIter {
         let cursor = self.read_cursor_at_char(range.start, false);
-        let node_gap_start = cursor.node.str.gap_start_chars as usize;
-        let local_pos = cursor.offset_chars;
-
-        let (at_start, skip) = if local_pos >= node_gap_start {
-            (false, local_pos - node_gap_start)
-        } else {
-            (true, local_pos)
-        };
-
-        SliceIter {
-            inner: ContentIter {
-                next: Some(cursor.node), at_start
-            },
-            skip,
-            take_len: range.end - range.start
-        }
+        SliceIter::from_cursor(self, &cursor, range)
     }
 
     /// Iterate through characters in the rope within the specified range. The range is specified
@@ -312,6 +552,87 @@ impl JumpRope {
         self.slice_substrings_with_len(range).chars()
     }
 
+    /// Iterate over the bytes in the UTF-8 encoding of the rope.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use jumprope::*;
+    /// let rope = JumpRope::from("κό");
+    /// assert_eq!(rope.bytes().collect::<Vec<u8>>(), "κό".as_bytes());
+    /// ```
+    pub fn bytes(&self) -> Bytes<'_> {
+        self.substrings_with_len().bytes()
+    }
+
+    /// Iterate over the bytes in the UTF-8 encoding of the specified range of the rope. The range is
+    /// specified in unicode characters.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use jumprope::*;
+    /// let rope = JumpRope::from("κόσμε");
+    /// assert_eq!(rope.slice_bytes(1..2).collect::<Vec<u8>>(), "ό".as_bytes());
+    /// ```
+    pub fn slice_bytes(&self, range: Range<usize>) -> BytesInRange<'_> {
+        self.slice_substrings_with_len(range).bytes()
+    }
+
+    /// Iterate over the UTF-16 code units (wchars) of the rope, like you'd get from a string in
+    /// Javascript, Java or C#.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use jumprope::*;
+    /// let rope = JumpRope::from("a𝕐");
+    /// assert_eq!(rope.utf16_units().collect::<Vec<u16>>(), "a𝕐".encode_utf16().collect::<Vec<u16>>());
+    /// ```
+    pub fn utf16_units(&self) -> Utf16Units<'_> {
+        self.substrings_with_len().utf16_units()
+    }
+
+    /// Iterate over the UTF-16 code units (wchars) of the specified range of the rope. The range is
+    /// specified in unicode characters.
+    pub fn slice_utf16_units(&self, range: Range<usize>) -> Utf16UnitsInRange<'_> {
+        self.slice_substrings_with_len(range).utf16_units()
+    }
+
+    /// Iterate over the substrings in the rope in reverse order, from the end of the rope to the
+    /// start. See [`substrings`](Self::substrings).
+    ///
+    /// Nodes only link forwards, so chunks are found in batches by searching back through the
+    /// rope. This is a bit slower than iterating forwards, but still *O(1)* amortized per chunk.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use jumprope::*;
+    /// let rope = JumpRope::from("oh hai");
+    /// let mut string = String::new();
+    /// for str in rope.substrings_rev() {
+    ///     string.insert_str(0, str);
+    /// }
+    /// assert_eq!(string, "oh hai");
+    /// ```
+    pub fn substrings_rev(&self) -> Rev<SubstringsInRange<'_>> {
+        self.slice_substrings(0..self.len_chars()).rev()
+    }
+
+    /// Iterate over the characters in the rope in reverse order.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use jumprope::*;
+    /// let rope = JumpRope::from("oh hai");
+    /// assert_eq!(rope.chars_rev().collect::<String>(), "iah ho");
+    /// ```
+    pub fn chars_rev(&self) -> Rev<CharsInRange<'_>> {
+        self.slice_chars(0..self.len_chars()).rev()
+    }
+
     // We also have a to_string implementation from Display, but that doesn't provide size hints.
     pub fn to_string(&self) -> String {
         let mut result = String::with_capacity(self.len_bytes());
@@ -322,11 +643,84 @@ impl JumpRope {
     }
 }
 
+/// These methods are only available if the `wchar_conversion` feature is enabled.
+#[cfg_attr(doc_cfg, doc(cfg(feature = "wchar_conversion")))]
+#[cfg(feature = "wchar_conversion")]
+impl JumpRope {
+    /// Iterate over the substrings in the specified wchar range of the rope.
+    ///
+    /// Returns an error if either end of the range splits a surrogate pair.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the range extends past the end of the rope.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use jumprope::*;
+    /// let rope = JumpRope::from("a𝕐b");
+    /// assert_eq!(rope.slice_substrings_at_wchar(1..4).unwrap().collect::<String>(), "𝕐b");
+    /// assert!(rope.slice_substrings_at_wchar(2..4).is_err());
+    /// ```
+    pub fn slice_substrings_at_wchar(&self, range: Range<usize>) -> Result<SubstringsInRange<'_>, SurrogatePairError> {
+        Ok(self.slice_substrings(self.wchar_range_to_chars(range)?))
+    }
+
+    /// Iterate over the characters in the specified wchar range of the rope.
+    ///
+    /// Returns an error if either end of the range splits a surrogate pair.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the range extends past the end of the rope.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use jumprope::*;
+    /// let rope = JumpRope::from("a𝕐b");
+    /// assert_eq!(rope.slice_chars_at_wchar(0..3).unwrap().collect::<String>(), "a𝕐");
+    /// assert!(rope.slice_chars_at_wchar(0..2).is_err());
+    /// ```
+    pub fn slice_chars_at_wchar(&self, range: Range<usize>) -> Result<CharsInRange<'_>, SurrogatePairError> {
+        Ok(self.slice_chars(self.wchar_range_to_chars(range)?))
+    }
+
+    /// Iterate over the UTF-16 code units (wchars) in the specified wchar range of the rope.
+    ///
+    /// The iterator yields exactly the code units in the range. If the range starts or ends in the
+    /// middle of a surrogate pair, only half of the pair is returned.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use jumprope::*;
+    /// let rope = JumpRope::from("a𝕐b");
+    /// let units: Vec<u16> = "a𝕐b".encode_utf16().collect();
+    /// assert_eq!(rope.slice_utf16_units_at_wchar(1..4).collect::<Vec<u16>>(), &units[1..4]);
+    /// ```
+    pub fn slice_utf16_units_at_wchar(&self, range: Range<usize>) -> Utf16UnitsInRange<'_> {
+        assert!(range.start <= range.end && range.end <= self.len_wchars(), "Invalid wchar range");
+
+        // wchars_to_chars rounds down to the start of the character containing each position.
+        let start = self.wchars_to_chars(range.start);
+        let end = self.wchars_to_chars(range.end);
+        let end = if self.chars_to_wchars(end) < range.end { end + 1 } else { end };
+
+        let mut iter = self.slice_substrings_with_len(start..end).utf16_units();
+        iter.skip = range.start - self.chars_to_wchars(start);
+        iter.take_len = Some(range.end - range.start);
+        iter
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use crate::fast_str_tools::*;
     use crate::JumpRope;
     use crate::jumprope::NODE_STR_SIZE;
+    use crate::test_utils::{rope_with_gaps, SAMPLE};
 
     fn check(rope: &JumpRope) {
         for (s, len) in rope.substrings_with_len() {
@@ -375,6 +769,145 @@ mod tests {
         );
     }
 
+    #[test]
+    fn iter_rev() {
+        let mut rope = JumpRope::from(SAMPLE);
+        rope.insert(5, "xx"); // Leave a gap in a node.
+        let s = rope.to_string();
+        let chars: Vec<char> = s.chars().collect();
+
+        assert_eq!(rope.chars_rev().collect::<String>(), s.chars().rev().collect::<String>());
+        assert_eq!(rope.substrings_rev().collect::<Vec<_>>(),
+            rope.substrings().collect::<Vec<_>>().into_iter().rev().collect::<Vec<_>>());
+
+        for start in 0..chars.len() {
+            for end in start..=chars.len().min(start + 25) {
+                let expected: String = chars[start..end].iter().rev().collect();
+                assert_eq!(rope.slice_chars(start..end).rev().collect::<String>(), expected);
+
+                // Alternate between the front and the back.
+                let mut iter = rope.slice_chars(start..end);
+                let mut front = String::new();
+                let mut back = vec![];
+                while let Some(c) = iter.next() {
+                    front.push(c);
+                    let Some(c) = iter.next_back() else { break; };
+                    back.push(c);
+                }
+                front.extend(back.iter().rev());
+                assert_eq!(front, chars[start..end].iter().collect::<String>());
+            }
+        }
+    }
+
+    #[test]
+    fn iter_rev_long() {
+        let mut rope = rope_with_gaps(&SAMPLE.repeat(10), 7);
+        // Leave empty halves in some nodes.
+        for pos in [0, 5, 77, 200, 201, 350, 590] {
+            rope.insert(pos, "x");
+            rope.remove(pos + 1..pos + 3);
+        }
+        let chars: Vec<char> = rope.chars().collect();
+        let len = chars.len();
+
+        let forwards = rope.substrings().collect::<Vec<_>>();
+        let mut backwards = rope.substrings_rev().collect::<Vec<_>>();
+        backwards.reverse();
+        assert_eq!(backwards, forwards);
+
+        for (start, end) in [(0, len), (1, len - 1), (len / 2, len), (0, 1), (len - 1, len), (len, len)] {
+            let expected: String = chars[start..end].iter().rev().collect();
+            assert_eq!(rope.slice_chars(start..end).rev().collect::<String>(), expected);
+        }
+    }
+
+    #[test]
+    fn iter_rev_empty() {
+        let rope = JumpRope::new();
+        assert_eq!(rope.chars_rev().next(), None);
+        assert_eq!(rope.substrings_rev().next(), None);
+        assert_eq!(rope.slice_chars(0..0).next_back(), None);
+        assert_eq!(rope.slice_substrings(0..0).next_back(), None);
+    }
+
+    #[test]
+    fn iter_rev_chunk_boundaries() {
+        let rope = rope_with_gaps(SAMPLE, 3);
+        let chars: Vec<char> = rope.chars().collect();
+        let mut boundaries = vec![0];
+        for (_, len) in rope.substrings_with_len() {
+            boundaries.push(boundaries.last().unwrap() + len);
+        }
+
+        for &start in &boundaries {
+            assert_eq!(rope.slice_chars(start..start).next_back(), None);
+            assert_eq!(rope.slice_substrings(start..start).next_back(), None);
+
+            for &end in boundaries.iter().filter(|&&end| end >= start) {
+                let mut expected = rope.slice_substrings(start..end).collect::<Vec<_>>();
+                expected.reverse();
+                assert_eq!(rope.slice_substrings(start..end).rev().collect::<Vec<_>>(), expected);
+
+                let expected: String = chars[start..end].iter().rev().collect();
+                assert_eq!(rope.slice_chars(start..end).rev().collect::<String>(), expected);
+            }
+        }
+
+        // The front and back meet exactly at a chunk boundary.
+        let mid = boundaries[boundaries.len() / 2];
+        let mut iter = rope.slice_substrings(0..rope.len_chars());
+        let mut front = 0;
+        while front < mid { front += count_chars(iter.next().unwrap()); }
+        let mut back = rope.len_chars();
+        while let Some(s) = iter.next_back() { back -= count_chars(s); }
+        assert_eq!(back, mid);
+        assert_eq!(iter.next(), None);
+    }
+
+    #[test]
+    fn iter_bytes_and_utf16() {
+        let mut rope = JumpRope::from(SAMPLE);
+        rope.insert(5, "xx");
+        let s = rope.to_string();
+        let chars: Vec<char> = s.chars().collect();
+
+        assert_eq!(rope.bytes().collect::<Vec<u8>>(), s.as_bytes());
+        assert_eq!(rope.utf16_units().collect::<Vec<u16>>(), s.encode_utf16().collect::<Vec<u16>>());
+
+        for start in 0..chars.len() {
+            for end in start..=chars.len().min(start + 25) {
+                let expected: String = chars[start..end].iter().collect();
+                assert_eq!(rope.slice_bytes(start..end).collect::<Vec<u8>>(), expected.as_bytes());
+                assert_eq!(rope.slice_utf16_units(start..end).collect::<Vec<u16>>(),
+                    expected.encode_utf16().collect::<Vec<u16>>());
+            }
+        }
+
+        #[cfg(feature = "wchar_conversion")] {
+            let units: Vec<u16> = s.encode_utf16().collect();
+            for start in 0..units.len() {
+                for end in start..=units.len().min(start + 25) {
+                    assert_eq!(rope.slice_utf16_units_at_wchar(start..end).collect::<Vec<u16>>(), &units[start..end]);
+
+                    // A position splits a surrogate pair if it lands on the low (trailing) half.
+                    let splits_pair = |pos: usize| units.get(pos).is_some_and(|u| (0xDC00..0xE000).contains(u));
+                    let result = rope.slice_substrings_at_wchar(start..end).map(|iter| iter.collect::<String>());
+                    if splits_pair(start) || splits_pair(end) {
+                        assert!(result.is_err());
+                    } else {
+                        assert_eq!(result.unwrap(), String::from_utf16(&units[start..end]).unwrap());
+                    }
+                }
+            }
+
+            assert_eq!(rope.to_utf16(), units);
+            assert_eq!(JumpRope::from_utf16(&units).unwrap(), rope);
+            assert!(JumpRope::from_utf16(&units[..3]).is_err());
+            assert_eq!(JumpRope::from_utf16_lossy(&units[..3]), "κό\u{FFFD}");
+        }
+    }
+
     #[test]
     fn iter_non_ascii() {
         check(&JumpRope::from("κό𝕐𝕆😘σμε"));
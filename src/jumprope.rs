@@ -15,10 +15,16 @@ use std::fmt::{Debug, Display, Formatter};
 use std::marker::PhantomData;
 use std::ops::Range;
 use std::ptr::null_mut;
+#[cfg(feature = "wchar_conversion")]
+use std::string::FromUtf16Error;
 use rand::prelude::*;
 use rand::Rng;
 use crate::fast_str_tools::*;
 use crate::gapbuffer::GapBuffer;
+use crate::anchors::AnchorSet;
+use crate::observers::{EditEvent, Observers};
+#[cfg(feature = "line_conversion")]
+use crate::utils::count_lines;
 // use crate::utils::*;
 // use crate::params::*;
 
@@ -55,13 +61,18 @@ type RopeRng = SmallRng;
 // height.
 #[repr(C)]
 pub struct JumpRope {
-    rng: RopeRng,
+    pub(super) rng: RopeRng,
+
+    // Positions in the rope which are updated automatically as the rope is edited. This is only
+    // allocated once the first anchor is created, so ropes without anchors don't pay for them.
+    pub(super) anchors: Option<Box<AnchorSet>>,
+
+    // Functions notified after each edit. These aren't cloned along with the rope.
+    pub(super) observers: Option<Box<Observers>>,
+
     // The total number of characters in the rope
     // num_chars: usize,
 
-    // The total number of bytes which the characters in the rope take up
-    num_bytes: usize,
-
     // The first node is inline. The height is the max height we've ever used in the rope + 1. The
     // highest entry points "past the end" of the list, including the entire list length.
     // TODO: Get rid of this and just rely on nexts out of here.
@@ -108,8 +119,16 @@ pub(super) struct SkipEntry {
     /// node.
     pub(super) skip_chars: usize,
 
+    /// The number of *bytes* between the start of the current node and the start of the next node.
+    pub(super) skip_bytes: usize,
+
     #[cfg(feature = "wchar_conversion")]
     pub(super) skip_pairs: usize,
+
+    /// The number of newline characters between the start of the current node and the start of
+    /// the next node.
+    #[cfg(feature = "line_conversion")]
+    pub(super) skip_lines: usize,
 }
 
 // Make sure nexts uses correct alignment. This should be guaranteed by repr(C)
@@ -133,8 +152,35 @@ impl SkipEntry {
         SkipEntry {
             node: null_mut(),
             skip_chars: 0,
+            skip_bytes: 0,
             #[cfg(feature = "wchar_conversion")]
-            skip_pairs: 0
+            skip_pairs: 0,
+            #[cfg(feature = "line_conversion")]
+            skip_lines: 0,
+        }
+    }
+}
+
+impl SkipEntry {
+    fn add_counts(&mut self, other: &SkipEntry) {
+        self.skip_chars += other.skip_chars;
+        self.skip_bytes += other.skip_bytes;
+        #[cfg(feature = "wchar_conversion")] {
+            self.skip_pairs += other.skip_pairs;
+        }
+        #[cfg(feature = "line_conversion")] {
+            self.skip_lines += other.skip_lines;
+        }
+    }
+
+    fn sub_counts(&mut self, other: &SkipEntry) {
+        self.skip_chars -= other.skip_chars;
+        self.skip_bytes -= other.skip_bytes;
+        #[cfg(feature = "wchar_conversion")] {
+            self.skip_pairs -= other.skip_pairs;
+        }
+        #[cfg(feature = "line_conversion")] {
+            self.skip_lines -= other.skip_lines;
         }
     }
 }
@@ -237,6 +283,11 @@ impl Node {
     pub(super) fn num_surrogate_pairs(&self) -> usize {
         self.first_next().skip_pairs
     }
+
+    #[cfg(feature = "line_conversion")]
+    pub(super) fn num_lines(&self) -> usize {
+        self.first_next().skip_lines
+    }
 }
 
 /// Cursors are a bit weird, and they deserve an explanation.
@@ -260,7 +311,10 @@ pub(super) struct MutCursor<'a> {
 
     // head_height: &'a mut u8,
     rng: &'a mut RopeRng,
-    num_bytes: &'a mut usize,
+
+    anchors: Option<&'a mut AnchorSet>,
+
+    observers: Option<&'a mut Observers>,
 
     phantom: PhantomData<&'a mut JumpRope>,
 }
@@ -286,7 +340,24 @@ impl<'a> MutCursor<'a> {
         std::ptr::eq(ptr, self.inner[MAX_HEIGHT].node)
     }
 
-    fn update_offsets(&mut self, height: usize, by_chars: isize, #[cfg(feature = "wchar_conversion")] by_pairs: isize) {
+    /// Grow the height of the head node (and the cursor along with it) to at least new_height.
+    fn grow_head_height(&mut self, new_height: usize) {
+        let mut head_height = self.head_height();
+        while head_height < new_height {
+            // The new top entry skips the whole list, like the entry below it.
+            unsafe {
+                let head = &mut (*self.inner[head_height].node);
+                head.nexts[head_height] = head.nexts[head_height - 1];
+            }
+
+            self.inner[head_height] = self.inner[head_height - 1];
+
+            head_height += 1;
+            self.set_height(head_height);
+        }
+    }
+
+    fn update_offsets(&mut self, height: usize, by_chars: isize, by_bytes: isize, #[cfg(feature = "wchar_conversion")] by_pairs: isize, #[cfg(feature = "line_conversion")] by_lines: isize) {
         for i in 0..height {
             unsafe {
                 // This is weird but makes sense when you realise the nexts in
@@ -296,19 +367,27 @@ impl<'a> MutCursor<'a> {
                 // Also adding a usize + isize is awful in rust :/
                 let entry = &mut (*self.inner[i].node).nexts[i];
                 entry.skip_chars = entry.skip_chars.wrapping_add(by_chars as usize);
+                entry.skip_bytes = entry.skip_bytes.wrapping_add(by_bytes as usize);
                 #[cfg(feature = "wchar_conversion")] {
                     entry.skip_pairs = entry.skip_pairs.wrapping_add(by_pairs as usize);
                 }
+                #[cfg(feature = "line_conversion")] {
+                    entry.skip_lines = entry.skip_lines.wrapping_add(by_lines as usize);
+                }
             }
         }
     }
 
-    fn move_within_node(&mut self, height: usize, by_chars: isize, #[cfg(feature = "wchar_conversion")] by_pairs: isize) {
+    fn move_within_node(&mut self, height: usize, by_chars: isize, by_bytes: isize, #[cfg(feature = "wchar_conversion")] by_pairs: isize, #[cfg(feature = "line_conversion")] by_lines: isize) {
         for e in &mut self.inner[..height] {
             e.skip_chars = e.skip_chars.wrapping_add(by_chars as usize);
+            e.skip_bytes = e.skip_bytes.wrapping_add(by_bytes as usize);
             #[cfg(feature = "wchar_conversion")] {
                 e.skip_pairs = e.skip_pairs.wrapping_add(by_pairs as usize);
             }
+            #[cfg(feature = "line_conversion")] {
+                e.skip_lines = e.skip_lines.wrapping_add(by_lines as usize);
+            }
         }
     }
 
@@ -330,11 +409,158 @@ impl<'a> MutCursor<'a> {
         entry.skip_chars + entry.skip_pairs
     }
 
+    /// Move the cursor forward by the specified number of characters. Like `mut_cursor_at_char`
+    /// with stick_end set, the cursor is left at the end of a node rather than at the start of
+    /// the next node.
+    ///
+    /// This only walks up the skip list as far as it needs to, so short moves are cheap.
+    pub(crate) fn advance_by_chars(&mut self, n: usize) {
+        let head_height = self.head_height();
+
+        // Find the lowest level whose current node contains the target position.
+        let mut height = 0;
+        loop {
+            let span = unsafe { (*self.inner[height].node).nexts[height].skip_chars };
+            if self.inner[height].skip_chars + n <= span { break; }
+            height += 1;
+            assert!(height < head_height, "Cannot move cursor past the end of the rope");
+        }
+
+        self.descend(height, self.inner[height].skip_chars + n);
+    }
+
+    /// Move the cursor backward by the specified number of characters. Like `advance_by_chars`,
+    /// this only walks up the skip list as far as it needs to, so short moves are cheap.
+    pub(crate) fn retreat_by_chars(&mut self, n: usize) {
+        assert!(n <= self.global_char_pos(), "Cannot move cursor past the start of the rope");
+
+        // Find the lowest level whose current node starts before the target position. The cursor
+        // is left at the end of nodes, so it can't be moved to the start of a node (other than the
+        // head).
+        let mut height = 0;
+        while self.inner[height].skip_chars <= n && !self.is_head(self.inner[height].node) {
+            height += 1;
+        }
+
+        self.descend(height, self.inner[height].skip_chars - n);
+    }
+
+    /// Move the cursor to `offset` chars after the start of the node at the specified height,
+    /// searching down from that node. The target position must be within the node's span.
+    fn descend(&mut self, mut height: usize, mut offset: usize) {
+        let head_height = self.head_height();
+        let old = self.inner[height];
+        let mut e = old.node;
+
+        // Positions here are relative to the start of the node we started descending from.
+        let mut bytes = 0;
+        #[cfg(feature = "wchar_conversion")]
+        let mut surrogate_pairs = 0;
+        #[cfg(feature = "line_conversion")]
+        let mut lines = 0;
+
+        let top = height;
+        loop {
+            let en = unsafe { &*e };
+            let next = en.nexts[height];
+            if offset > next.skip_chars {
+                // Go right.
+                offset -= next.skip_chars;
+                bytes += next.skip_bytes;
+                #[cfg(feature = "wchar_conversion")] {
+                    surrogate_pairs += next.skip_pairs;
+                }
+                #[cfg(feature = "line_conversion")] {
+                    lines += next.skip_lines;
+                }
+                e = next.node;
+                assert!(!e.is_null(), "Internal constraint violation: Reached rope end prematurely");
+            } else {
+                // Record this and go down.
+                self.inner[height] = SkipEntry {
+                    node: e,
+                    skip_chars: offset,
+                    skip_bytes: bytes,
+                    #[cfg(feature = "wchar_conversion")]
+                    skip_pairs: surrogate_pairs,
+                    #[cfg(feature = "line_conversion")]
+                    skip_lines: lines,
+                };
+
+                if height != 0 {
+                    height -= 1;
+                } else {
+                    // Convert the recorded positions into the distance from each node to the
+                    // cursor, like mut_cursor_at_char.
+                    bytes += en.str.count_bytes(offset);
+                    #[cfg(feature = "wchar_conversion")] {
+                        surrogate_pairs += en.str.count_surrogate_pairs(offset);
+                    }
+                    #[cfg(feature = "line_conversion")] {
+                        lines += en.str.count_lines_before(offset);
+                    }
+                    for entry in &mut self.inner[0..=top] {
+                        entry.skip_bytes = bytes - entry.skip_bytes;
+                        #[cfg(feature = "wchar_conversion")] {
+                            entry.skip_pairs = surrogate_pairs - entry.skip_pairs;
+                        }
+                        #[cfg(feature = "line_conversion")] {
+                            entry.skip_lines = lines - entry.skip_lines;
+                        }
+                    }
+                    break;
+                }
+            }
+        }
+
+        // The levels above are still in the same nodes. They just need their offsets moved along
+        // (forwards or backwards).
+        let new = self.inner[top];
+        for entry in &mut self.inner[top + 1..head_height] {
+            entry.skip_chars = entry.skip_chars.wrapping_add(new.skip_chars.wrapping_sub(old.skip_chars));
+            entry.skip_bytes = entry.skip_bytes.wrapping_add(new.skip_bytes.wrapping_sub(old.skip_bytes));
+            #[cfg(feature = "wchar_conversion")] {
+                entry.skip_pairs = entry.skip_pairs.wrapping_add(new.skip_pairs.wrapping_sub(old.skip_pairs));
+            }
+            #[cfg(feature = "line_conversion")] {
+                entry.skip_lines = entry.skip_lines.wrapping_add(new.skip_lines.wrapping_sub(old.skip_lines));
+            }
+        }
+    }
+
+    /// The cursor's position in the rope, in each unit.
+    fn position(&self) -> SkipEntry {
+        self.inner[self.head_height() - 1]
+    }
+
+    /// The total length of the rope.
+    pub(crate) fn totals(&self) -> SkipEntry {
+        unsafe { (*self.inner[MAX_HEIGHT].node).nexts[self.head_height() - 1] }
+    }
+
+    /// If the rope has any observers, returns the position of the cursor and the length of the
+    /// rope, for describing an edit made at the cursor once it's done.
+    fn observe_edit(&self) -> Option<(SkipEntry, SkipEntry)> {
+        self.observers.is_some().then(|| (self.position(), self.totals()))
+    }
+
+    /// Notify the rope's observers about an edit made at the cursor. observed is the value
+    /// returned by observe_edit before the edit was made.
+    fn notify(&mut self, observed: Option<(SkipEntry, SkipEntry)>, inserted: &str) {
+        if let Some((start, before)) = observed {
+            let event = EditEvent::new(&start, &before, &self.totals(), inserted);
+            if let Some(observers) = self.observers.as_deref_mut() {
+                observers.notify(&event);
+            }
+        }
+    }
+
     pub(crate) fn local_char_pos(&self) -> usize {
         self.inner[0].skip_chars
     }
 }
 
+#[derive(Clone, Copy)]
 pub(crate) struct ReadCursor<'a> {
     pub(super) node: &'a Node,
 
@@ -342,6 +568,9 @@ pub(crate) struct ReadCursor<'a> {
     /// node.
     pub(super) offset_chars: usize,
 
+    /// The number of bytes in the rope before the cursor.
+    pub(super) global_bytes: usize,
+
     // We can populate this, but we aren't using it anywhere.
     // #[cfg(feature = "wchar_conversion")]
     // pub(super) offset_pairs: usize,
@@ -350,6 +579,10 @@ pub(crate) struct ReadCursor<'a> {
     #[cfg(feature = "wchar_conversion")]
     global_pairs: usize,
 
+    /// The number of newlines in the rope before the cursor.
+    #[cfg(feature = "line_conversion")]
+    global_lines: usize,
+
     phantom: PhantomData<&'a JumpRope>
 }
 
@@ -363,7 +596,8 @@ impl JumpRope {
     fn new_with_rng(rng: RopeRng) -> Self {
         JumpRope {
             rng,
-            num_bytes: 0,
+            anchors: None,
+            observers: None,
             // nexts: [SkipEntry::new(); MAX_HEIGHT],
 
             // We don't ever store characters in the head node, but the height
@@ -416,6 +650,58 @@ impl JumpRope {
         rope
     }
 
+    /// Build a rope containing `s` by filling up nodes in order and linking them together
+    /// directly. This skips all the skip list lookups and node splits which happen when the text
+    /// is inserted normally.
+    pub(crate) fn new_from_str_bulk(mut s: &str) -> Self {
+        let mut r = Self::new();
+
+        // The last node seen at each height, and the counts from the start of that node to the
+        // current position.
+        let mut prev: [*mut Node; MAX_HEIGHT+1] = [&mut r.head; MAX_HEIGHT+1];
+        let mut counts = [SkipEntry::new(); MAX_HEIGHT+1];
+        let mut max_height = 0;
+
+        while !s.is_empty() {
+            let mut len = min(s.len(), NODE_STR_SIZE);
+            while !s.is_char_boundary(len) { len -= 1; }
+            let (chunk, rest) = s.split_at(len);
+            s = rest;
+
+            let height = random_height(&mut r.rng);
+            max_height = max_height.max(height as usize);
+            let new_node = Box::into_raw(Box::new(Node::new_with_height(height, chunk)));
+
+            for i in 0..height as usize {
+                unsafe { (*prev[i]).nexts[i] = SkipEntry { node: new_node, ..counts[i] }; }
+                prev[i] = new_node;
+                counts[i] = SkipEntry::new();
+            }
+
+            let node_counts = SkipEntry {
+                node: null_mut(),
+                skip_chars: count_chars(chunk),
+                skip_bytes: chunk.len(),
+                #[cfg(feature = "wchar_conversion")]
+                skip_pairs: count_utf16_surrogates(chunk),
+                #[cfg(feature = "line_conversion")]
+                skip_lines: count_lines(chunk),
+            };
+            for c in counts.iter_mut() {
+                c.add_counts(&node_counts);
+            }
+        }
+
+        // Point everything past the end of the list. The head's extra top entry spans the whole
+        // list.
+        r.head.height = max_height as u8 + 1;
+        for i in 0..=max_height {
+            unsafe { (*prev[i]).nexts[i] = counts[i]; }
+        }
+
+        r
+    }
+
     /// Return the length of the rope in unicode characters. Note this is not the same as either
     /// the number of bytes the characters take, or the number of grapheme clusters in the string.
     ///
@@ -470,9 +756,12 @@ impl JumpRope {
         let mut height = self.head.height as usize - 1;
 
         let mut offset_chars = char_pos; // How many more chars to skip
+        let mut global_bytes = 0;
 
         #[cfg(feature = "wchar_conversion")]
         let mut global_pairs = 0; // Current wchar pos from the start of the rope
+        #[cfg(feature = "line_conversion")]
+        let mut global_lines = 0;
 
         loop { // while height >= 0
             let en = unsafe { &*e };
@@ -482,9 +771,13 @@ impl JumpRope {
                 // Go right.
                 // debug_assert!(e == &self.head || !en.str.is_empty());
                 offset_chars -= skip;
+                global_bytes += next.skip_bytes;
                 #[cfg(feature = "wchar_conversion")] {
                     global_pairs += next.skip_pairs;
                 }
+                #[cfg(feature = "line_conversion")] {
+                    global_lines += next.skip_lines;
+                }
                 e = next.node;
                 assert!(!e.is_null(), "Internal constraint violation: Reached rope end prematurely");
             } else {
@@ -497,15 +790,23 @@ impl JumpRope {
                     #[cfg(feature = "wchar_conversion")] {
                         global_pairs += offset_pairs;
                     }
+                    #[cfg(feature = "line_conversion")] {
+                        global_lines += en.str.count_lines_before(offset_chars);
+                    }
+
+                    global_bytes += en.str.count_bytes(offset_chars);
 
                     return ReadCursor {
                         node: unsafe { &*e },
                         offset_chars,
+                        global_bytes,
                         // #[cfg(feature = "wchar_conversion")]
                         // offset_pairs,
                         phantom: PhantomData,
                         #[cfg(feature = "wchar_conversion")]
                         global_pairs,
+                        #[cfg(feature = "line_conversion")]
+                        global_lines,
                     }
                 }
             }
@@ -520,9 +821,12 @@ impl JumpRope {
         let mut height = head_height - 1;
 
         let mut offset = char_pos; // How many more chars to skip
+        let mut bytes = 0; // Current byte pos from the start of the rope
 
         #[cfg(feature = "wchar_conversion")]
         let mut surrogate_pairs = 0; // Current wchar pos from the start of the rope
+        #[cfg(feature = "line_conversion")]
+        let mut lines = 0; // Number of newlines from the start of the rope
 
         // It would be nice to pop this into a function, but miri gets confused if we pass the node
         // pointer out of this method. So I'm keeping this inline.
@@ -530,11 +834,15 @@ impl JumpRope {
             inner: [SkipEntry {
                 node: e,
                 skip_chars: 0,
+                skip_bytes: 0,
                 #[cfg(feature = "wchar_conversion")]
-                skip_pairs: 0
+                skip_pairs: 0,
+                #[cfg(feature = "line_conversion")]
+                skip_lines: 0,
             }; MAX_HEIGHT+1],
             rng: &mut self.rng,
-            num_bytes: &mut self.num_bytes,
+            anchors: self.anchors.as_deref_mut(),
+            observers: self.observers.as_deref_mut().filter(|o| !o.is_empty()),
             phantom: PhantomData,
         };
 
@@ -548,9 +856,13 @@ impl JumpRope {
                 // This breaks miri for some reason.
                 // assert!(e == &mut self.head || !en.str.is_empty());
                 offset -= skip;
+                bytes += next.skip_bytes;
                 #[cfg(feature = "wchar_conversion")] {
                     surrogate_pairs += next.skip_pairs;
                 }
+                #[cfg(feature = "line_conversion")] {
+                    lines += next.skip_lines;
+                }
                 e = next.node;
                 assert!(!e.is_null(), "Internal constraint violation: Reached rope end prematurely");
             } else {
@@ -559,13 +871,23 @@ impl JumpRope {
                     // node: e as *mut Node, // This is pretty gross
                     node: e,
                     skip_chars: offset,
+                    skip_bytes: bytes,
                     #[cfg(feature = "wchar_conversion")]
-                    skip_pairs: surrogate_pairs
+                    skip_pairs: surrogate_pairs,
+                    #[cfg(feature = "line_conversion")]
+                    skip_lines: lines,
                 };
 
                 if height != 0 {
                     height -= 1;
                 } else {
+                    // The entries store the global byte position where we went down. Convert
+                    // them to the distance from each node to the cursor.
+                    bytes += en.str.count_bytes(offset);
+                    for entry in &mut cursor.inner[0..head_height] {
+                        entry.skip_bytes = bytes - entry.skip_bytes;
+                    }
+
                     #[cfg(feature = "wchar_conversion")] {
                         // Add on the wchar length at the current node.
                         surrogate_pairs += en.str.count_surrogate_pairs(offset);
@@ -575,6 +897,15 @@ impl JumpRope {
                             }
                         }
                     }
+                    #[cfg(feature = "line_conversion")] {
+                        // Same trick for newlines.
+                        lines += en.str.count_lines_before(offset);
+                        if lines > 0 {
+                            for entry in &mut cursor.inner[0..head_height] {
+                                entry.skip_lines = lines - entry.skip_lines;
+                            }
+                        }
+                    }
                     break;
                 }
             }
@@ -619,6 +950,109 @@ impl JumpRope {
         };
     }
 
+    /// Find the char position corresponding to the specified byte offset. Returns `None` if the
+    /// byte offset lands inside a UTF-8 encoded character.
+    pub(crate) fn count_chars_at_byte(&self, byte_pos: usize) -> Option<usize> {
+        assert!(byte_pos <= self.len_bytes());
+
+        let mut height = self.head.height as usize - 1;
+        let mut e: *const Node = &self.head;
+
+        let mut offset = byte_pos; // How many more bytes to skip
+
+        let mut char_pos = 0; // Char pos from the start of the rope
+
+        loop {
+            let en = unsafe { &*e };
+            let next = en.nexts[height];
+            if offset > next.skip_bytes {
+                // Go right.
+                offset -= next.skip_bytes;
+                char_pos += next.skip_chars;
+                e = next.node;
+                assert!(!e.is_null(), "Internal constraint violation: Reached rope end prematurely");
+            } else {
+                // Go down.
+                if height != 0 {
+                    height -= 1;
+                } else {
+                    return en.str.count_chars_in_bytes(offset)
+                        .map(|local_chars| char_pos + local_chars);
+                }
+            }
+        };
+    }
+
+    /// Find the contiguous chunk of text (one half of a node's gap buffer) which contains the
+    /// specified byte offset. Returns the chunk and the byte offset of the start of the chunk.
+    ///
+    /// If byte_pos is at the end of the rope, this returns the last chunk in the rope.
+    #[cfg(any(feature = "graphemes", feature = "regex"))]
+    pub(crate) fn chunk_at_byte(&self, byte_pos: usize) -> (&str, usize) {
+        assert!(byte_pos <= self.len_bytes());
+
+        let mut height = self.head.height as usize - 1;
+        let mut e: *const Node = &self.head;
+
+        let mut offset = byte_pos; // How many more bytes to skip
+
+        loop {
+            let en = unsafe { &*e };
+            let next = en.nexts[height];
+            if offset >= next.skip_bytes && !next.node.is_null() {
+                // Go right.
+                offset -= next.skip_bytes;
+                e = next.node;
+            } else if height != 0 {
+                // Go down.
+                height -= 1;
+            } else {
+                let node_start = byte_pos - offset;
+                let gap_bytes = en.str.gap_start_bytes as usize;
+                let end = en.str.end_as_str();
+                return if offset < gap_bytes || end.is_empty() {
+                    (en.str.start_as_str(), node_start)
+                } else {
+                    (end, node_start + gap_bytes)
+                };
+            }
+        };
+    }
+
+    /// Find the char position of the start of the specified line. The line must exist in the rope.
+    #[cfg(feature = "line_conversion")]
+    pub(crate) fn count_chars_at_line(&self, line: usize) -> usize {
+        assert!(line < self.len_lines());
+        if line == 0 { return 0; }
+
+        let mut height = self.head.height as usize - 1;
+        let mut e: *const Node = &self.head;
+
+        let mut offset = line; // How many more newlines to skip
+
+        let mut char_pos = 0; // Char pos from the start of the rope
+
+        loop {
+            let en = unsafe { &*e };
+            let next = en.nexts[height];
+            if offset > next.skip_lines {
+                // Go right.
+                offset -= next.skip_lines;
+                char_pos += next.skip_chars;
+                e = next.node;
+                assert!(!e.is_null(), "Internal constraint violation: Reached rope end prematurely");
+            } else {
+                // Go down.
+                if height != 0 {
+                    height -= 1;
+                } else {
+                    char_pos += en.str.count_chars_in_lines(offset);
+                    return char_pos;
+                }
+            }
+        };
+    }
+
     /// Create a cursor pointing wchar characters into the rope
     #[cfg(feature = "wchar_conversion")]
     pub(crate) fn mut_cursor_at_wchar(&mut self, wchar_pos: usize, stick_end: bool) -> MutCursor {
@@ -631,16 +1065,23 @@ impl JumpRope {
         let mut offset = wchar_pos; // How many more chars to skip
 
         let mut char_pos = 0; // Char pos from the start of the rope
+        let mut bytes = 0; // Byte pos from the start of the rope
+        #[cfg(feature = "line_conversion")]
+        let mut lines = 0; // Number of newlines from the start of the rope
 
         let mut cursor = MutCursor {
             inner: [SkipEntry {
                 node: e,
                 skip_chars: 0,
+                skip_bytes: 0,
                 #[cfg(feature = "wchar_conversion")]
-                skip_pairs: 0
+                skip_pairs: 0,
+                #[cfg(feature = "line_conversion")]
+                skip_lines: 0,
             }; MAX_HEIGHT+1],
             rng: &mut self.rng,
-            num_bytes: &mut self.num_bytes,
+            anchors: self.anchors.as_deref_mut(),
+            observers: self.observers.as_deref_mut().filter(|o| !o.is_empty()),
             phantom: PhantomData,
         };
 
@@ -653,6 +1094,10 @@ impl JumpRope {
                 // assert!(e == &self.head || !en.str.is_empty());
                 offset -= skip;
                 char_pos += next.skip_chars;
+                bytes += next.skip_bytes;
+                #[cfg(feature = "line_conversion")] {
+                    lines += next.skip_lines;
+                }
                 e = next.node;
                 assert!(!e.is_null(), "Internal constraint violation: Reached rope end prematurely");
             } else {
@@ -660,17 +1105,29 @@ impl JumpRope {
                 cursor.inner[height] = SkipEntry {
                     node: e,
                     skip_chars: char_pos,
-                    skip_pairs: offset
+                    skip_bytes: bytes,
+                    skip_pairs: offset,
+                    #[cfg(feature = "line_conversion")]
+                    skip_lines: lines,
                 };
 
                 if height != 0 {
                     height -= 1;
                 } else {
-                    char_pos += en.str.count_chars_in_wchars(offset);
+                    let local_chars = en.str.count_chars_in_wchars(offset);
+                    char_pos += local_chars;
+                    bytes += en.str.count_bytes(local_chars);
+                    #[cfg(feature = "line_conversion")] {
+                        lines += en.str.count_lines_before(local_chars);
+                    }
                     for entry in &mut cursor.inner[0..head_height] {
                         let skip_chars = char_pos - entry.skip_chars;
                         entry.skip_chars = skip_chars;
+                        entry.skip_bytes = bytes - entry.skip_bytes;
                         entry.skip_pairs -= skip_chars;
+                        #[cfg(feature = "line_conversion")] {
+                            entry.skip_lines = lines - entry.skip_lines;
+                        }
                     }
                     break;
                 }
@@ -682,32 +1139,40 @@ impl JumpRope {
         cursor
     }
 
+    #[allow(unused)]
     fn mut_cursor_at_start(&mut self) -> MutCursor<'_> {
         MutCursor {
             inner: [SkipEntry {
                 node: &mut self.head,
                 skip_chars: 0,
+                skip_bytes: 0,
                 #[cfg(feature = "wchar_conversion")]
-                skip_pairs: 0
+                skip_pairs: 0,
+                #[cfg(feature = "line_conversion")]
+                skip_lines: 0,
             }; MAX_HEIGHT+1],
             rng: &mut self.rng,
-            num_bytes: &mut self.num_bytes,
+            anchors: self.anchors.as_deref_mut(),
+            observers: self.observers.as_deref_mut().filter(|o| !o.is_empty()),
             phantom: PhantomData,
         }
     }
 
-    fn mut_cursor_at_end(&mut self) -> MutCursor {
+    pub(super) fn mut_cursor_at_end(&mut self) -> MutCursor {
         self.mut_cursor_at_char(self.len_chars(), true)
     }
 
-    fn insert_node_at(cursor: &mut MutCursor, contents: &str, num_chars: usize, update_cursor: bool, #[cfg(feature = "wchar_conversion")] num_pairs: usize) {
-        // println!("Insert_node_at {} len {}", contents.len(), self.num_bytes);
+    fn insert_node_at(cursor: &mut MutCursor, contents: &str, num_chars: usize, update_cursor: bool, #[cfg(feature = "wchar_conversion")] num_pairs: usize, #[cfg(feature = "line_conversion")] num_lines: usize) {
         // assert!(contents.len() < NODE_STR_SIZE);
         debug_assert_eq!(count_chars(contents), num_chars);
         #[cfg(feature = "wchar_conversion")] {
             debug_assert_eq!(count_utf16_surrogates(contents), num_pairs);
         }
+        #[cfg(feature = "line_conversion")] {
+            debug_assert_eq!(count_lines(contents), num_lines);
+        }
         debug_assert!(num_chars <= NODE_STR_SIZE);
+        let num_bytes = contents.len();
 
         // TODO: Pin this sucka.
         // let new_node = Pin::new(Node::alloc());
@@ -720,44 +1185,41 @@ impl JumpRope {
 
         // let new_height = unsafe { (*new_node).height as usize };
 
-        let mut head_height = cursor.head_height();
-        while head_height <= new_height {
-            // TODO: Why do we copy here? Explain it in a comment. This is
-            // currently lifted from the C code.
-            // cursor.head_nexts[head_height] = cursor.head_nexts[head_height - 1];
-            unsafe {
-                let head = &mut (*cursor.inner[head_height].node);
-                head.nexts[head_height] = head.nexts[head_height - 1];
-            }
-
-            cursor.inner[head_height] = cursor.inner[head_height - 1];
-
-            // *cursor.head_height += 1; // Ends up 1 more than the max node height.
-            head_height += 1;
-            cursor.set_height(head_height);
-        }
+        // The head ends up 1 more than the max node height.
+        cursor.grow_head_height(new_height + 1);
+        let head_height = cursor.head_height();
 
         for i in 0..new_height {
             let prev_skip = unsafe { &mut (*cursor.inner[i].node).nexts[i] };
             let nexts = unsafe { &mut (*new_node).nexts };
             nexts[i].node = prev_skip.node;
             nexts[i].skip_chars = num_chars + prev_skip.skip_chars - cursor.inner[i].skip_chars;
+            nexts[i].skip_bytes = num_bytes + prev_skip.skip_bytes - cursor.inner[i].skip_bytes;
 
             prev_skip.node = new_node;
             prev_skip.skip_chars = cursor.inner[i].skip_chars;
+            prev_skip.skip_bytes = cursor.inner[i].skip_bytes;
 
             #[cfg(feature = "wchar_conversion")] {
                 nexts[i].skip_pairs = num_pairs + prev_skip.skip_pairs - cursor.inner[i].skip_pairs;
                 prev_skip.skip_pairs = cursor.inner[i].skip_pairs;
             }
+            #[cfg(feature = "line_conversion")] {
+                nexts[i].skip_lines = num_lines + prev_skip.skip_lines - cursor.inner[i].skip_lines;
+                prev_skip.skip_lines = cursor.inner[i].skip_lines;
+            }
 
             // & move the iterator to the end of the newly inserted node.
             if update_cursor {
                 cursor.inner[i].node = new_node;
                 cursor.inner[i].skip_chars = num_chars;
+                cursor.inner[i].skip_bytes = num_bytes;
                 #[cfg(feature = "wchar_conversion")] {
                     cursor.inner[i].skip_pairs = num_pairs;
                 }
+                #[cfg(feature = "line_conversion")] {
+                    cursor.inner[i].skip_lines = num_lines;
+                }
             }
         }
 
@@ -765,23 +1227,38 @@ impl JumpRope {
             // I don't know why miri needs me to use nexts[] rather than nexts_mut() here but ??.
             unsafe {
                 (*cursor.inner[i].node).nexts[i].skip_chars += num_chars;
+                (*cursor.inner[i].node).nexts[i].skip_bytes += num_bytes;
                 #[cfg(feature = "wchar_conversion")] {
                     (*cursor.inner[i].node).nexts[i].skip_pairs += num_pairs;
                 }
+                #[cfg(feature = "line_conversion")] {
+                    (*cursor.inner[i].node).nexts[i].skip_lines += num_lines;
+                }
             }
             if update_cursor {
                 cursor.inner[i].skip_chars += num_chars;
+                cursor.inner[i].skip_bytes += num_bytes;
                 #[cfg(feature = "wchar_conversion")] {
                     cursor.inner[i].skip_pairs += num_pairs;
                 }
+                #[cfg(feature = "line_conversion")] {
+                    cursor.inner[i].skip_lines += num_lines;
+                }
             }
         }
+    }
 
-        // self.nexts[self.head.height as usize - 1].skip_chars += num_chars;
-        *cursor.num_bytes += contents.len();
+    pub(super) fn insert_at_cursor(cursor: &mut MutCursor, contents: &str) {
+        if cursor.observers.is_none() {
+            Self::insert_at_cursor_raw(cursor, contents);
+        } else if !contents.is_empty() {
+            let observed = cursor.observe_edit();
+            Self::insert_at_cursor_raw(cursor, contents);
+            cursor.notify(observed, contents);
+        }
     }
 
-    fn insert_at_cursor(cursor: &mut MutCursor, contents: &str) {
+    fn insert_at_cursor_raw(cursor: &mut MutCursor, contents: &str) {
         if contents.is_empty() { return; }
         // iter contains how far (in characters) into the current element to
         // skip. Figure out how much that is in bytes.
@@ -800,6 +1277,15 @@ impl JumpRope {
             let mut num_inserted_pairs = if num_inserted_bytes != num_inserted_chars {
             count_utf16_surrogates(contents)
         } else { 0 };
+        #[cfg(feature = "line_conversion")]
+        let mut num_inserted_lines = count_lines(contents);
+
+        if cursor.anchors.is_some() {
+            let pos = cursor.global_char_pos();
+            if let Some(anchors) = cursor.anchors.as_deref_mut() {
+                anchors.insert(pos, num_inserted_chars);
+            }
+        }
 
         // Adding this short circuit makes the code about 2% faster for 1% more code
         unsafe {
@@ -807,16 +1293,13 @@ impl JumpRope {
                 // Short circuit. If we can just insert all the content right here in the gap, do so.
                 (*e).str.insert_in_gap(contents);
 
-                #[cfg(feature = "wchar_conversion")] {
-                    cursor.update_offsets(head_height, num_inserted_chars as isize, num_inserted_pairs as isize);
-                    cursor.move_within_node(head_height, num_inserted_chars as isize, num_inserted_pairs as isize);
-                }
-                #[cfg(not(feature = "wchar_conversion"))] {
-                    cursor.update_offsets(head_height, num_inserted_chars as isize);
-                    cursor.move_within_node(head_height, num_inserted_chars as isize);
-                }
+                cursor.update_offsets(head_height, num_inserted_chars as isize, num_inserted_bytes as isize,
+                    #[cfg(feature = "wchar_conversion")] (num_inserted_pairs as isize),
+                    #[cfg(feature = "line_conversion")] (num_inserted_lines as isize));
+                cursor.move_within_node(head_height, num_inserted_chars as isize, num_inserted_bytes as isize,
+                    #[cfg(feature = "wchar_conversion")] (num_inserted_pairs as isize),
+                    #[cfg(feature = "line_conversion")] (num_inserted_lines as isize));
 
-                *cursor.num_bytes += num_inserted_bytes;
                 return;
             }
 
@@ -848,8 +1331,11 @@ impl JumpRope {
                             *e = SkipEntry {
                                 node: next,
                                 skip_chars: 0,
+                                skip_bytes: 0,
                                 #[cfg(feature = "wchar_conversion")]
-                                skip_pairs: 0
+                                skip_pairs: 0,
+                                #[cfg(feature = "line_conversion")]
+                                skip_lines: 0,
                             };
                         }
                         e = next;
@@ -864,17 +1350,14 @@ impl JumpRope {
                 let c = &mut (*e).str;
                 c.try_insert(offset_bytes, contents).unwrap();
 
-                *cursor.num_bytes += num_inserted_bytes;
                 // .... aaaand update all the offset amounts.
 
-                #[cfg(feature = "wchar_conversion")] {
-                    cursor.update_offsets(head_height, num_inserted_chars as isize, num_inserted_pairs as isize);
-                    cursor.move_within_node(head_height, num_inserted_chars as isize, num_inserted_pairs as isize);
-                }
-                #[cfg(not(feature = "wchar_conversion"))] {
-                    cursor.update_offsets(head_height, num_inserted_chars as isize);
-                    cursor.move_within_node(head_height, num_inserted_chars as isize);
-                }
+                cursor.update_offsets(head_height, num_inserted_chars as isize, num_inserted_bytes as isize,
+                    #[cfg(feature = "wchar_conversion")] (num_inserted_pairs as isize),
+                    #[cfg(feature = "line_conversion")] (num_inserted_lines as isize));
+                cursor.move_within_node(head_height, num_inserted_chars as isize, num_inserted_bytes as isize,
+                    #[cfg(feature = "wchar_conversion")] (num_inserted_pairs as isize),
+                    #[cfg(feature = "line_conversion")] (num_inserted_lines as isize));
             } else {
                 // There isn't room. We'll need to add at least one new node to the rope.
 
@@ -886,6 +1369,8 @@ impl JumpRope {
                 let mut num_end_chars: usize = 0;
                 #[cfg(feature = "wchar_conversion")]
                 let mut num_end_pairs: usize = 0;
+                #[cfg(feature = "line_conversion")]
+                let mut num_end_lines: usize = 0;
 
                 // let end_str = if num_end_bytes > 0 {
                 if num_end_bytes > 0 {
@@ -898,12 +1383,14 @@ impl JumpRope {
                     #[cfg(feature = "wchar_conversion")] {
                         num_end_pairs = (*e).num_surrogate_pairs() - (*e).str.gap_start_surrogate_pairs as usize;
                         debug_assert_eq!(num_end_pairs, count_utf16_surrogates((*e).str.end_as_str()));
-                        cursor.update_offsets(head_height, -(num_end_chars as isize), -(num_end_pairs as isize));
                     }
-                    #[cfg(not(feature = "wchar_conversion"))]
-                    cursor.update_offsets(head_height, -(num_end_chars as isize));
-
-                    *cursor.num_bytes -= num_end_bytes;
+                    #[cfg(feature = "line_conversion")] {
+                        num_end_lines = (*e).num_lines() - (*e).str.gap_start_lines as usize;
+                        debug_assert_eq!(num_end_lines, count_lines((*e).str.end_as_str()));
+                    }
+                    cursor.update_offsets(head_height, -(num_end_chars as isize), -(num_end_bytes as isize),
+                        #[cfg(feature = "wchar_conversion")] (-(num_end_pairs as isize)),
+                        #[cfg(feature = "line_conversion")] (-(num_end_lines as isize)));
                 }
 
                 // Now we insert new nodes containing the new character data. The
@@ -918,7 +1405,9 @@ impl JumpRope {
                     // Find the first index after STR_SIZE bytes
 
                     if remainder.len() <= NODE_STR_SIZE {
-                        Self::insert_node_at(cursor, remainder, num_inserted_chars, true, #[cfg(feature = "wchar_conversion")] num_inserted_pairs);
+                        Self::insert_node_at(cursor, remainder, num_inserted_chars, true,
+                            #[cfg(feature = "wchar_conversion")] num_inserted_pairs,
+                            #[cfg(feature = "line_conversion")] num_inserted_lines);
                         break;
                     } else {
                         // Find a suitable cut point. We should take as many characters as we can fit in
@@ -944,14 +1433,25 @@ impl JumpRope {
 
                         let (next, rem) = remainder.split_at(byte_pos);
                         assert!(!next.is_empty());
-                        Self::insert_node_at(cursor, next, char_pos, true, #[cfg(feature = "wchar_conversion")] pairs);
+
+                        #[cfg(feature = "line_conversion")]
+                        let lines = count_lines(next);
+                        #[cfg(feature = "line_conversion")] {
+                            num_inserted_lines -= lines;
+                        }
+
+                        Self::insert_node_at(cursor, next, char_pos, true,
+                            #[cfg(feature = "wchar_conversion")] pairs,
+                            #[cfg(feature = "line_conversion")] lines);
                         remainder = rem;
                     }
                 }
 
                 if num_end_bytes > 0 {
                     let end_str = (*e).str.take_rest();
-                    Self::insert_node_at(cursor, end_str, num_end_chars, false, #[cfg(feature = "wchar_conversion")] num_end_pairs);
+                    Self::insert_node_at(cursor, end_str, num_end_chars, false,
+                        #[cfg(feature = "wchar_conversion")] num_end_pairs,
+                        #[cfg(feature = "line_conversion")] num_end_lines);
                 }
                 // if let Some(end_str) = end_str {
                 //     Self::insert_node_at(cursor, end_str, num_end_chars, false, #[cfg(feature = "wchar_conversion")] num_end_pairs);
@@ -962,8 +1462,67 @@ impl JumpRope {
         }
     }
 
-    fn del_at_cursor(cursor: &mut MutCursor, mut length: usize) {
+    /// Move any content after the cursor in the current node into a new node, so the cursor sits
+    /// at the end of its node.
+    fn split_node_at_cursor(cursor: &mut MutCursor) {
+        let offset_chars = cursor.local_char_pos();
+        let e = cursor.here_mut_ptr();
+        let head_height = cursor.head_height();
+
+        unsafe {
+            let num_end_chars = (*e).num_chars() - offset_chars;
+            if num_end_chars == 0 { return; }
+
+            let offset_bytes = (*e).str.count_bytes(offset_chars);
+            (*e).str.move_gap(offset_bytes);
+            let num_end_bytes = (*e).str.len_bytes() - offset_bytes;
+            #[cfg(feature = "wchar_conversion")]
+            let num_end_pairs = (*e).num_surrogate_pairs() - (*e).str.gap_start_surrogate_pairs as usize;
+            #[cfg(feature = "line_conversion")]
+            let num_end_lines = (*e).num_lines() - (*e).str.gap_start_lines as usize;
+
+            cursor.update_offsets(head_height, -(num_end_chars as isize), -(num_end_bytes as isize),
+                #[cfg(feature = "wchar_conversion")] (-(num_end_pairs as isize)),
+                #[cfg(feature = "line_conversion")] (-(num_end_lines as isize)));
+
+            let end_str = (*e).str.take_rest();
+            Self::insert_node_at(cursor, end_str, num_end_chars, false,
+                #[cfg(feature = "wchar_conversion")] num_end_pairs,
+                #[cfg(feature = "line_conversion")] num_end_lines);
+        }
+    }
+
+    /// Delete length characters after the cursor. If deleted is passed, the removed text is
+    /// appended to it.
+    pub(super) fn del_at_cursor(cursor: &mut MutCursor, length: usize, deleted: Option<&mut String>) {
+        if cursor.observers.is_none() {
+            Self::del_at_cursor_raw(cursor, length, deleted);
+        } else if length > 0 {
+            let observed = cursor.observe_edit();
+            Self::del_at_cursor_raw(cursor, length, deleted);
+            cursor.notify(observed, "");
+        }
+    }
+
+    /// Replace the length characters after the cursor with contents. Observers are notified once
+    /// for the whole edit, rather than for the delete and the insert separately.
+    pub(super) fn replace_at_cursor(cursor: &mut MutCursor, length: usize, contents: &str, deleted: Option<&mut String>) {
+        if length == 0 && contents.is_empty() { return; }
+
+        let observed = cursor.observe_edit();
+        Self::del_at_cursor_raw(cursor, length, deleted);
+        Self::insert_at_cursor_raw(cursor, contents);
+        cursor.notify(observed, contents);
+    }
+
+    fn del_at_cursor_raw(cursor: &mut MutCursor, mut length: usize, mut deleted: Option<&mut String>) {
         if length == 0 { return; }
+        if cursor.anchors.is_some() {
+            let pos = cursor.global_char_pos();
+            if let Some(anchors) = cursor.anchors.as_deref_mut() {
+                anchors.remove_range(pos, pos + length);
+            }
+        }
         let mut offset_chars = cursor.local_char_pos();
         let mut node = cursor.here_ptr();
         unsafe {
@@ -981,51 +1540,71 @@ impl JumpRope {
                 let removed = std::cmp::min(length, num_chars - offset_chars);
                 assert!(removed > 0);
 
+                if let Some(out) = deleted.as_deref_mut() {
+                    (*node).str.push_chars_to(out, offset_chars, removed);
+                }
+
                 // TODO: Figure out a better way to calculate this.
                 #[cfg(feature = "wchar_conversion")]
                     let removed_pairs = (*node).str.count_surrogate_pairs(offset_chars + removed)
                     - (*node).str.count_surrogate_pairs(offset_chars);
+                #[cfg(feature = "line_conversion")]
+                    let removed_lines = (*node).str.count_lines_before(offset_chars + removed)
+                    - (*node).str.count_lines_before(offset_chars);
 
                 let height = (*node).height as usize;
-                if removed < num_chars || cursor.is_head(node) {
+                let removed_bytes = if removed < num_chars || cursor.is_head(node) {
                     // Just trim the node down.
                     let s = &mut (*node).str;
                     let removed_bytes = s.remove_chars(offset_chars, removed);
-                    *cursor.num_bytes -= removed_bytes;
 
                     for s in (*node).nexts_mut() {
                         s.skip_chars -= removed;
+                        s.skip_bytes -= removed_bytes;
                         #[cfg(feature = "wchar_conversion")] {
                             s.skip_pairs -= removed_pairs;
                         }
+                        #[cfg(feature = "line_conversion")] {
+                            s.skip_lines -= removed_lines;
+                        }
                     }
+                    removed_bytes
                 } else {
                     // Remove the node from the skip list. This works because the cursor must be
                     // pointing from the previous element to the start of this element.
                     assert_ne!(cursor.inner[0].node, node);
 
+                    let removed_bytes = (*node).str.len_bytes();
                     for i in 0..(*node).height as usize {
                         let s = &mut (*cursor.inner[i].node).nexts_mut()[i];
                         s.node = (*node).nexts[i].node;
                         s.skip_chars += (*node).nexts[i].skip_chars - removed;
+                        s.skip_bytes += (*node).nexts[i].skip_bytes - removed_bytes;
                         #[cfg(feature = "wchar_conversion")] {
                             s.skip_pairs += (*node).nexts[i].skip_pairs - removed_pairs;
                         }
+                        #[cfg(feature = "line_conversion")] {
+                            s.skip_lines += (*node).nexts[i].skip_lines - removed_lines;
+                        }
                     }
 
-                    *cursor.num_bytes -= (*node).str.len_bytes();
                     let next = (*node).first_next().node;
                     // Node::free(node);
                     drop(Box::from_raw(node));
                     node = next;
-                }
+                    removed_bytes
+                };
 
                 for i in height..cursor.head_height() {
                     let s = &mut (*cursor.inner[i].node).nexts[i];
                     s.skip_chars -= removed;
+                    s.skip_bytes -= removed_bytes;
                     #[cfg(feature = "wchar_conversion")] {
                         s.skip_pairs -= removed_pairs;
                     }
+                    #[cfg(feature = "line_conversion")] {
+                        s.skip_lines -= removed_lines;
+                    }
                 }
 
                 length -= removed;
@@ -1083,7 +1662,7 @@ impl PartialEq for JumpRope {
     // but also we could add comparison functions with a single &str and stuff
     // very easily.
     fn eq(&self, other: &JumpRope) -> bool {
-        if self.num_bytes != other.num_bytes
+        if self.len_bytes() != other.len_bytes()
                 || self.len_chars() != other.len_chars() {
             return false
         }
@@ -1167,16 +1746,38 @@ impl<'a> Extend<&'a str> for JumpRope {
     }
 }
 
+/// Cloning a rope copies each node's memory directly and relinks the copies, so the clone has the
+/// same shape as the original. This is *O(n)*, but it doesn't need to re-scan or re-insert any
+/// of the text.
+///
+/// If you need to take copies often (eg to hand each version of a document to another thread),
+/// use [`snapshot`](Self::snapshot) instead. While snapshots are in use, the rope keeps a shared
+/// copy of its content up to date, so taking another snapshot is *O(1)*.
 impl Clone for JumpRope {
     fn clone(&self) -> Self {
-        // This method could be a little bit more efficient, but I think improving clone()
-        // performance isn't worth the extra effort.
         let mut r = JumpRope::new();
-        let mut cursor = r.mut_cursor_at_start();
-        for node in self.node_iter_at_start() {
-            JumpRope::insert_at_cursor(&mut cursor, node.as_str_1());
-            JumpRope::insert_at_cursor(&mut cursor, node.as_str_2());
+        r.anchors = self.anchors.clone();
+        r.head.str = self.head.str.clone();
+        r.head.height = self.head.height;
+        r.head.nexts = self.head.nexts;
+
+        // The copied nexts still point into self. Each node at each level gets patched to point
+        // to its copied successor once we reach it. Any entries left over at the end point past
+        // the end of the list, which are already null.
+        let mut prev: [*mut Node; MAX_HEIGHT+1] = [&mut r.head; MAX_HEIGHT+1];
+        for node in self.node_iter_at_start().skip(1) {
+            let new_node = Box::into_raw(Box::new(Node {
+                str: node.str.clone(),
+                height: node.height,
+                nexts: node.nexts,
+            }));
+
+            for (i, p) in prev[..node.height as usize].iter_mut().enumerate() {
+                unsafe { (**p).nexts[i].node = new_node; }
+                *p = new_node;
+            }
         }
+
         r
     }
 }
@@ -1214,6 +1815,10 @@ impl JumpRope {
     ///
     /// Any attempt to delete past the end of the rope will be silently ignored.
     ///
+    /// # Panics
+    ///
+    /// Panics if `range.start > range.end`.
+    ///
     /// # Example
     ///
     /// ```
@@ -1224,13 +1829,14 @@ impl JumpRope {
     /// ```
     pub fn remove(&mut self, mut range: Range<usize>) {
         // if cfg!(debug_assertions) { self.check(); }
+        assert!(range.start <= range.end, "Range start {} is after range end {}", range.start, range.end);
 
         range.end = range.end.min(self.len_chars());
         if range.start >= range.end { return; }
 
         // We need to stick_end so we can delete entries.
         let mut cursor = self.mut_cursor_at_char(range.start, true);
-        Self::del_at_cursor(&mut cursor, range.end - range.start);
+        Self::del_at_cursor(&mut cursor, range.end - range.start, None);
 
         debug_assert_eq!(cursor.global_char_pos(), range.start);
     }
@@ -1238,6 +1844,10 @@ impl JumpRope {
     /// Replace the specified range with new content. This is equivalent to calling
     /// [`remove`](Self::remove) followed by [`insert`](Self::insert), but it is simpler and faster.
     ///
+    /// # Panics
+    ///
+    /// Panics if `range.start > range.end`.
+    ///
     /// # Example
     ///
     /// ```
@@ -1247,19 +1857,190 @@ impl JumpRope {
     /// assert_eq!(rope.to_string(), "Hi Duane!");
     /// ```
     pub fn replace(&mut self, range: Range<usize>, content: &str) {
+        self.replace_internal(range, content, None);
+    }
+
+    /// Delete a span of unicode characters from the rope, returning the deleted text. This is
+    /// equivalent to calling [`remove`](Self::remove), but the removed content is collected as it
+    /// is deleted.
+    ///
+    /// Any attempt to delete past the end of the rope will be silently ignored.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `range.start > range.end`.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use jumprope::*;
+    /// let mut rope = JumpRope::from("Whoa dawg!");
+    /// assert_eq!(rope.remove_returning(4..9), " dawg");
+    /// assert_eq!(rope.to_string(), "Whoa!");
+    /// ```
+    pub fn remove_returning(&mut self, range: Range<usize>) -> String {
+        self.replace_returning(range, "")
+    }
+
+    /// Replace the specified range with new content, returning the text which was replaced.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `range.start > range.end`.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use jumprope::*;
+    /// let mut rope = JumpRope::from("Hi Mike!");
+    /// assert_eq!(rope.replace_returning(3..7, "Duane"), "Mike");
+    /// assert_eq!(rope.to_string(), "Hi Duane!");
+    /// ```
+    pub fn replace_returning(&mut self, range: Range<usize>, content: &str) -> String {
+        let mut deleted = String::new();
+        self.replace_internal(range, content, Some(&mut deleted));
+        deleted
+    }
+
+    /// Replace the specified range with new content. If deleted is passed, the removed text is
+    /// appended to it. Returns the (clamped) position of the edit.
+    pub(crate) fn replace_internal(&mut self, range: Range<usize>, content: &str, deleted: Option<&mut String>) -> usize {
+        assert!(range.start <= range.end, "Range start {} is after range end {}", range.start, range.end);
         let len = self.len_chars();
         let pos = usize::min(range.start, len);
         let del_len = usize::min(range.end, len) - pos;
 
         let mut cursor = self.mut_cursor_at_char(pos, true);
-        if del_len > 0 {
-            Self::del_at_cursor(&mut cursor, del_len);
+        Self::replace_at_cursor(&mut cursor, del_len, content, deleted);
+
+        debug_assert_eq!(cursor.global_char_pos(), pos + count_chars(content));
+        pos
+    }
+
+    /// Split the rope in two at the specified unicode character offset. After this call, `self`
+    /// contains the characters before `char_pos`, and the returned rope contains everything
+    /// after it.
+    ///
+    /// This runs in *O(log n)* time. The skip list is cut in two rather than copying any text.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `char_pos` is past the end of the rope.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use jumprope::*;
+    /// let mut rope = JumpRope::from("Hello world!");
+    /// let rest = rope.split_off(5);
+    /// assert_eq!(rope, "Hello");
+    /// assert_eq!(rest, " world!");
+    /// ```
+    pub fn split_off(&mut self, char_pos: usize) -> JumpRope {
+        assert!(char_pos <= self.len_chars());
+
+        // Anchors in the split off content move to the end of this rope.
+        let len = self.len_chars();
+        if let Some(anchors) = self.anchors.as_deref_mut() {
+            anchors.remove_range(char_pos, len);
         }
-        if !content.is_empty() {
-            Self::insert_at_cursor(&mut cursor, content);
+
+        let mut other = JumpRope::new();
+        let mut cursor = self.mut_cursor_at_char(char_pos, true);
+        let observed = cursor.observe_edit();
+        Self::split_node_at_cursor(&mut cursor);
+
+        // The cursor now sits at a node boundary. Everything after the cursor at each level gets
+        // moved into other.
+        let head_height = cursor.head_height();
+        other.head.height = head_height as u8;
+        for i in 0..head_height {
+            let prev = unsafe { &mut (*cursor.inner[i].node).nexts[i] };
+            let mut entry = *prev;
+            entry.sub_counts(&cursor.inner[i]);
+            other.head.nexts[i] = entry;
+
+            *prev = cursor.inner[i];
+            prev.node = null_mut();
         }
 
-        debug_assert_eq!(cursor.global_char_pos(), pos + count_chars(content));
+        if char_pos < len {
+            cursor.notify(observed, "");
+        }
+
+        other
+    }
+
+    /// Move all the content of `other` onto the end of this rope.
+    ///
+    /// This runs in *O(log n)* time. The nodes from `other` are linked into this rope rather than
+    /// copying any text.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use jumprope::*;
+    /// let mut rope = JumpRope::from("Hello");
+    /// rope.append(JumpRope::from(" world!"));
+    /// assert_eq!(rope, "Hello world!");
+    /// ```
+    pub fn append(&mut self, mut other: JumpRope) {
+        if other.is_empty() { return; }
+
+        let other_height = other.head.height as usize;
+        let other_len = other.head.nexts[other_height - 1];
+
+        let mut cursor = self.mut_cursor_at_end();
+
+        // Observers are notified once with all of other's content. The snapshot mirror takes
+        // other's content directly, so the content is only copied into a string for listeners.
+        let observed = cursor.observe_edit();
+        let mut inserted = String::new();
+        if let Some(observers) = cursor.observers.as_deref_mut() {
+            if let Some(mirror) = observers.mirror_for_edit() {
+                mirror.append(&other);
+            }
+            if observers.has_listeners() {
+                inserted = other.to_string();
+            }
+        }
+
+        // The head node of other may contain some content of its own. That gets copied across, and
+        // the rest of the nodes are linked in directly after it.
+        let other_head = *other.head.first_next();
+        Self::insert_at_cursor_raw(&mut cursor, other.head.as_str_1());
+        Self::insert_at_cursor_raw(&mut cursor, other.head.as_str_2());
+        cursor.grow_head_height(other_height);
+
+        // The cursor names the last node at each level, which all currently point to the end of
+        // the list.
+        for i in 0..cursor.head_height() {
+            let prev = unsafe { &mut (*cursor.inner[i].node).nexts[i] };
+            debug_assert!(prev.node.is_null());
+            if i < other_height {
+                prev.node = other.head.nexts[i].node;
+                prev.add_counts(&other.head.nexts[i]);
+            } else {
+                prev.add_counts(&other_len);
+            }
+            prev.sub_counts(&other_head);
+        }
+
+        let end = cursor.global_char_pos();
+        if let Some(anchors) = cursor.anchors.as_deref_mut() {
+            anchors.insert(end, other_len.skip_chars - other_head.skip_chars);
+        }
+
+        if let Some((start, before)) = observed.filter(|_| !inserted.is_empty()) {
+            let event = EditEvent::new(&start, &before, &cursor.totals(), &inserted);
+            if let Some(observers) = cursor.observers.as_deref_mut() {
+                observers.notify_listeners(&event);
+            }
+        }
+
+        // The nodes are owned by self now. Unlink them from other so they aren't freed twice.
+        other.head.height = 1;
+        other.head.nexts[0] = SkipEntry::new();
     }
 
     /// Get the number of bytes used for the UTF8 representation of the rope. This will always match
@@ -1279,21 +2060,25 @@ impl JumpRope {
     /// let rope = JumpRope::from(str);
     /// assert_eq!(rope.len_bytes(), str.len());
     /// ```
-    pub fn len_bytes(&self) -> usize { self.num_bytes }
+    pub fn len_bytes(&self) -> usize {
+        self.head.nexts[self.head.height as usize - 1].skip_bytes
+    }
 
     /// Returns `true` if the rope contains no elements.
-    pub fn is_empty(&self) -> bool { self.num_bytes == 0 }
+    pub fn is_empty(&self) -> bool { self.len_bytes() == 0 }
 
     pub fn check(&self) {
         assert!(self.head.height >= 1);
         assert!(self.head.height < MAX_HEIGHT_U8 + 1);
 
         let skip_over = &self.head.nexts[self.head.height as usize - 1];
-        // println!("Skip over skip chars {}, num bytes {}", skip_over.skip_chars, self.num_bytes);
-        assert!(skip_over.skip_chars <= self.num_bytes as usize);
+        assert!(skip_over.skip_chars <= skip_over.skip_bytes);
         #[cfg(feature = "wchar_conversion")] {
             assert!(skip_over.skip_pairs <= skip_over.skip_chars);
         }
+        #[cfg(feature = "line_conversion")] {
+            assert!(skip_over.skip_lines <= skip_over.skip_chars);
+        }
         assert!(skip_over.node.is_null());
 
         // The offsets store the total distance travelled since the start.
@@ -1307,6 +2092,8 @@ impl JumpRope {
         let mut num_chars = 0;
         #[cfg(feature = "wchar_conversion")]
         let mut num_pairs = 0;
+        #[cfg(feature = "line_conversion")]
+        let mut num_lines = 0;
 
         for n in self.node_iter_at_start() {
             // println!("visiting {:?}", n.as_str());
@@ -1319,18 +2106,27 @@ impl JumpRope {
             for (i, entry) in iter[0..n.height as usize].iter_mut().enumerate() {
                 assert_eq!(entry.node as *const Node, n as *const Node);
                 assert_eq!(entry.skip_chars, num_chars);
+                assert_eq!(entry.skip_bytes, num_bytes);
                 #[cfg(feature = "wchar_conversion")] {
                     assert_eq!(entry.skip_pairs, num_pairs);
                 }
+                #[cfg(feature = "line_conversion")] {
+                    assert_eq!(entry.skip_lines, num_lines);
+                }
 
                 // println!("replacing entry {:?} with {:?}", entry, n.nexts()[i].node);
                 entry.node = n.nexts[i].node;
                 entry.skip_chars += n.nexts[i].skip_chars;
+                entry.skip_bytes += n.nexts[i].skip_bytes;
                 #[cfg(feature = "wchar_conversion")] {
                     entry.skip_pairs += n.nexts[i].skip_pairs;
                 }
+                #[cfg(feature = "line_conversion")] {
+                    entry.skip_lines += n.nexts[i].skip_lines;
+                }
             }
 
+            assert_eq!(n.str.len_bytes(), n.first_next().skip_bytes);
             num_bytes += n.str.len_bytes();
             num_chars += n.num_chars();
 
@@ -1338,23 +2134,34 @@ impl JumpRope {
                 assert_eq!(n.num_surrogate_pairs(), n.str.count_surrogate_pairs(n.num_chars()));
                 num_pairs += n.num_surrogate_pairs();
             }
+
+            #[cfg(feature = "line_conversion")] {
+                assert_eq!(n.num_lines(), n.str.count_lines_before(n.num_chars()));
+                num_lines += n.num_lines();
+            }
         }
 
         for entry in iter[0..self.head.height as usize].iter() {
             // println!("{:?}", entry);
             assert!(entry.node.is_null());
             assert_eq!(entry.skip_chars, num_chars);
+            assert_eq!(entry.skip_bytes, num_bytes);
             #[cfg(feature = "wchar_conversion")] {
                 assert_eq!(entry.skip_pairs, num_pairs);
             }
+            #[cfg(feature = "line_conversion")] {
+                assert_eq!(entry.skip_lines, num_lines);
+            }
         }
 
-        // println!("self bytes: {}, count bytes {}", self.num_bytes, num_bytes);
-        assert_eq!(self.num_bytes, num_bytes);
+        assert_eq!(self.len_bytes(), num_bytes);
         assert_eq!(self.len_chars(), num_chars);
         #[cfg(feature = "wchar_conversion")] {
             assert_eq!(self.len_wchars(), num_chars + num_pairs);
         }
+        #[cfg(feature = "line_conversion")] {
+            assert_eq!(self.len_lines(), num_lines + 1);
+        }
     }
 
     /// This method counts the number of bytes of memory allocated in the rope. This is purely for
@@ -1386,11 +2193,11 @@ impl JumpRope {
     #[allow(unused)]
     // pub fn print(&self) {
     pub(crate) fn print(&self) {
-        println!("chars: {}\tbytes: {}\theight: {}", self.len_chars(), self.num_bytes, self.head.height);
+        println!("chars: {}\tbytes: {}\theight: {}", self.len_chars(), self.len_bytes(), self.head.height);
 
         print!("HEAD:");
         for s in self.head.nexts() {
-            print!(" |{} ", s.skip_chars);
+            print!(" |{} [{}b]", s.skip_chars, s.skip_bytes);
             #[cfg(feature = "wchar_conversion")] {
                 print!("({}) ", s.skip_pairs);
             }
@@ -1413,6 +2220,156 @@ impl JumpRope {
     }
 }
 
+/// The error returned when a byte offset passed to one of the byte-based editing or conversion
+/// methods (like [`JumpRope::insert_at_byte`]) lands in the middle of a multi-byte UTF-8 encoded
+/// character.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CharBoundaryError {
+    /// The offending byte offset.
+    pub byte_pos: usize,
+}
+
+impl Display for CharBoundaryError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "byte offset {} is not on a UTF-8 character boundary", self.byte_pos)
+    }
+}
+
+impl std::error::Error for CharBoundaryError {}
+
+/// The error returned when a wchar (UTF-16 code unit) offset passed to one of the checked wchar
+/// methods (like [`JumpRope::wchar_to_char`]) lands between the two halves of a surrogate pair.
+#[cfg_attr(doc_cfg, doc(cfg(feature = "wchar_conversion")))]
+#[cfg(feature = "wchar_conversion")]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SurrogatePairError {
+    /// The offending wchar offset.
+    pub wchar_pos: usize,
+}
+
+#[cfg(feature = "wchar_conversion")]
+impl Display for SurrogatePairError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "wchar offset {} splits a UTF-16 surrogate pair", self.wchar_pos)
+    }
+}
+
+#[cfg(feature = "wchar_conversion")]
+impl std::error::Error for SurrogatePairError {}
+
+impl JumpRope {
+    /// Convert a byte offset in the UTF-8 representation of the rope to a unicode character
+    /// offset. This runs in *O(log n)* time.
+    ///
+    /// Returns an error if the byte offset lands inside a multi-byte character.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `byte_pos` is past the end of the rope.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use jumprope::*;
+    /// let rope = JumpRope::from("κόσμε");
+    /// assert_eq!(rope.byte_to_char(4), Ok(2));
+    /// assert!(rope.byte_to_char(3).is_err());
+    /// ```
+    pub fn byte_to_char(&self, byte_pos: usize) -> Result<usize, CharBoundaryError> {
+        self.count_chars_at_byte(byte_pos).ok_or(CharBoundaryError { byte_pos })
+    }
+
+    /// Convert a unicode character offset to the corresponding byte offset in the UTF-8
+    /// representation of the rope. This runs in *O(log n)* time.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `char_pos` is past the end of the rope.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use jumprope::*;
+    /// let rope = JumpRope::from("κόσμε");
+    /// assert_eq!(rope.char_to_byte(2), 4);
+    /// ```
+    pub fn char_to_byte(&self, char_pos: usize) -> usize {
+        self.read_cursor_at_char(char_pos, true).global_bytes
+    }
+
+    /// Insert new content into the rope at the specified byte offset. Returns the insertion
+    /// position in characters.
+    ///
+    /// If the position names a location past the end of the rope, it is truncated. Returns an
+    /// error (and leaves the rope unmodified) if the byte offset lands inside a multi-byte
+    /// character.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use jumprope::*;
+    /// let mut rope = JumpRope::from("κόσμε");
+    /// assert_eq!(rope.insert_at_byte(4, "-"), Ok(2));
+    /// assert_eq!(rope, "κό-σμε");
+    /// assert!(rope.insert_at_byte(1, "-").is_err());
+    /// ```
+    pub fn insert_at_byte(&mut self, byte_pos: usize, contents: &str) -> Result<usize, CharBoundaryError> {
+        let pos = self.byte_to_char(byte_pos.min(self.len_bytes()))?;
+        self.insert(pos, contents);
+        Ok(pos)
+    }
+
+    /// Delete the span of the rope specified by the passed byte range.
+    ///
+    /// Any attempt to delete past the end of the rope will be silently ignored. Returns an error
+    /// (and leaves the rope unmodified) if either end of the range lands inside a multi-byte
+    /// character.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use jumprope::*;
+    /// let mut rope = JumpRope::from("κόσμε");
+    /// rope.remove_bytes(4..8).unwrap();
+    /// assert_eq!(rope, "κόε");
+    /// ```
+    pub fn remove_bytes(&mut self, range: Range<usize>) -> Result<(), CharBoundaryError> {
+        let range = self.byte_range_to_chars(range)?;
+        self.remove(range);
+        Ok(())
+    }
+
+    /// Replace the specified byte range with new content. This is equivalent to calling
+    /// [`remove_bytes`](Self::remove_bytes) followed by
+    /// [`insert_at_byte`](Self::insert_at_byte).
+    ///
+    /// Returns an error (and leaves the rope unmodified) if either end of the range lands inside
+    /// a multi-byte character.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use jumprope::*;
+    /// let mut rope = JumpRope::from("κόσμε");
+    /// rope.replace_bytes(4..8, "ς").unwrap();
+    /// assert_eq!(rope, "κόςε");
+    /// ```
+    pub fn replace_bytes(&mut self, range: Range<usize>, content: &str) -> Result<(), CharBoundaryError> {
+        let range = self.byte_range_to_chars(range)?;
+        self.replace(range, content);
+        Ok(())
+    }
+
+    fn byte_range_to_chars(&self, range: Range<usize>) -> Result<Range<usize>, CharBoundaryError> {
+        let len = self.len_bytes();
+        let start = self.byte_to_char(range.start.min(len))?;
+        let end = if range.end <= range.start { start } else {
+            self.byte_to_char(range.end.min(len))?
+        };
+        Ok(start..end)
+    }
+}
+
 /// These methods are only available if the `wchar_conversion` feature is enabled.
 #[cfg_attr(doc_cfg, doc(cfg(feature = "wchar_conversion")))]
 #[cfg(feature = "wchar_conversion")]
@@ -1433,7 +2390,8 @@ impl JumpRope {
     ///
     /// **NOTE:** This method's behaviour is undefined if the wchar offset is invalid. Eg, given a
     /// rope with contents `𐆚` (a single character with wchar length 2), `wchars_to_chars(1)` is
-    /// undefined and may panic / change in future versions of diamond types.
+    /// undefined and may panic / change in future versions of diamond types. Use
+    /// [`wchar_to_char`](Self::wchar_to_char) to check the offset.
     pub fn wchars_to_chars(&self, wchars: usize) -> usize {
         if self.is_ascii_only() {
             wchars
@@ -1442,6 +2400,81 @@ impl JumpRope {
         }
     }
 
+    /// Convert a wchar index to a unicode character count, checking that the offset is valid.
+    ///
+    /// Returns an error if the wchar offset lands between the two halves of a surrogate pair.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `wchar_pos` is past the end of the rope.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use jumprope::*;
+    /// let rope = JumpRope::from("a𝕐b");
+    /// assert_eq!(rope.wchar_to_char(3), Ok(2));
+    /// assert_eq!(rope.wchar_to_char(2), Err(SurrogatePairError { wchar_pos: 2 }));
+    /// ```
+    pub fn wchar_to_char(&self, wchar_pos: usize) -> Result<usize, SurrogatePairError> {
+        assert!(wchar_pos <= self.len_wchars(), "wchar offset {} is past the end of the rope", wchar_pos);
+        let char_pos = self.wchars_to_chars(wchar_pos);
+        if self.chars_to_wchars(char_pos) == wchar_pos {
+            Ok(char_pos)
+        } else {
+            Err(SurrogatePairError { wchar_pos })
+        }
+    }
+
+    /// Convert a range of wchars into the equivalent range of unicode characters. Returns an error
+    /// if either end of the range splits a surrogate pair.
+    pub(crate) fn wchar_range_to_chars(&self, range: Range<usize>) -> Result<Range<usize>, SurrogatePairError> {
+        let start = self.wchar_to_char(range.start)?;
+        let end = if range.end <= range.start { start } else {
+            self.wchar_to_char(range.end)?
+        };
+        Ok(start..end)
+    }
+
+    /// Encode the contents of the rope as UTF-16, like a string in Javascript, Java or C#.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use jumprope::*;
+    /// let rope = JumpRope::from("a𝕐b");
+    /// assert_eq!(rope.to_utf16(), "a𝕐b".encode_utf16().collect::<Vec<u16>>());
+    /// ```
+    pub fn to_utf16(&self) -> Vec<u16> {
+        let mut result = Vec::with_capacity(self.len_wchars());
+        result.extend(self.utf16_units());
+        result
+    }
+
+    /// Create a new rope from UTF-16 encoded content.
+    ///
+    /// Returns an error if the content contains unpaired (lone) surrogates, which can't be
+    /// represented in a rust string. Use [`from_utf16_lossy`](Self::from_utf16_lossy) to replace
+    /// them with U+FFFD instead.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use jumprope::*;
+    /// let units: Vec<u16> = "a𝕐b".encode_utf16().collect();
+    /// assert_eq!(JumpRope::from_utf16(&units).unwrap(), "a𝕐b");
+    /// assert!(JumpRope::from_utf16(&units[..2]).is_err());
+    /// ```
+    pub fn from_utf16(units: &[u16]) -> Result<Self, FromUtf16Error> {
+        String::from_utf16(units).map(Self::from)
+    }
+
+    /// Create a new rope from UTF-16 encoded content, replacing any unpaired surrogates with the
+    /// replacement character (U+FFFD).
+    pub fn from_utf16_lossy(units: &[u16]) -> Self {
+        Self::from(String::from_utf16_lossy(units))
+    }
+
     /// Insert the given utf8 string into the rope at the specified wchar position.
     /// This is compatible with NSString, Javascript, etc.
     ///
@@ -1483,7 +2516,7 @@ impl JumpRope {
         let mut cursor = self.mut_cursor_at_wchar(range.start, true);
         let char_start = cursor.global_char_pos();
 
-        Self::del_at_cursor(&mut cursor, char_end - char_start);
+        Self::del_at_cursor(&mut cursor, char_end - char_start, None);
 
         debug_assert_eq!(cursor.wchar_pos(), range.start);
     }
@@ -1495,13 +2528,136 @@ impl JumpRope {
     /// `replace_at_wchar(1..2, ...)` is undefined and may panic / change in future versions of
     /// diamond types.
     pub fn replace_at_wchar(&mut self, range: Range<usize>, content: &str) {
-        // TODO: Optimize this. This method should work similarly to replace(), where we create
-        // a single cursor and use it in both contexts.
-        if !range.is_empty() {
-            self.remove_at_wchar(range.clone());
-        }
-        if !content.is_empty() {
-            self.insert_at_wchar(range.start, content);
-        }
+        let len = self.len_wchars();
+        let start = self.wchars_to_chars(range.start.min(len));
+        let end = self.wchars_to_chars(range.end.min(len));
+        self.replace_internal(start..end, content, None);
+    }
+}
+
+/// These methods are only available if the `line_conversion` feature is enabled.
+///
+/// Lines are separated by `'\n'` characters. Each line starts immediately after a newline
+/// character (or at the start of the document), and includes its trailing newline (if any). So an
+/// empty rope has 1 line, and a rope containing `"a\nb\n"` has 3 lines.
+#[cfg_attr(doc_cfg, doc(cfg(feature = "line_conversion")))]
+#[cfg(feature = "line_conversion")]
+impl JumpRope {
+    /// Return the number of lines in the rope. This is always 1 more than the number of newline
+    /// characters in the document.
+    ///
+    /// This method returns the length in constant-time (*O(1)*).
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use jumprope::*;
+    /// assert_eq!(JumpRope::new().len_lines(), 1);
+    /// assert_eq!(JumpRope::from("one\ntwo\n").len_lines(), 3);
+    /// ```
+    pub fn len_lines(&self) -> usize {
+        self.head.nexts[self.head.height as usize - 1].skip_lines + 1
     }
-}
\ No newline at end of file
+
+    /// Convert a unicode character offset to the (zero-based) index of the line containing that
+    /// character.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `char_pos` is past the end of the rope.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use jumprope::*;
+    /// let rope = JumpRope::from("ab\ncd");
+    /// assert_eq!(rope.char_to_line(2), 0); // The newline is part of the first line
+    /// assert_eq!(rope.char_to_line(3), 1);
+    /// ```
+    pub fn char_to_line(&self, char_pos: usize) -> usize {
+        self.read_cursor_at_char(char_pos, true).global_lines
+    }
+
+    /// Convert a (zero-based) line index to the unicode character offset of the start of that
+    /// line.
+    ///
+    /// For convenience, `line_to_char(rope.len_lines())` is also valid, and returns the length of
+    /// the rope in characters.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `line > rope.len_lines()`.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use jumprope::*;
+    /// let rope = JumpRope::from("ab\ncd");
+    /// assert_eq!(rope.line_to_char(0), 0);
+    /// assert_eq!(rope.line_to_char(1), 3);
+    /// assert_eq!(rope.line_to_char(2), 5);
+    /// ```
+    pub fn line_to_char(&self, line: usize) -> usize {
+        let len_lines = self.len_lines();
+        assert!(line <= len_lines, "Line {line} is out of bounds");
+
+        if line == len_lines { self.len_chars() }
+        else { self.count_chars_at_line(line) }
+    }
+
+    /// Insert new content at the specified line and column. The column is specified in unicode
+    /// characters from the start of the line.
+    ///
+    /// If the column is past the end of the line, the content is inserted at the end of the line
+    /// (before its newline).
+    ///
+    /// Returns the insertion position in characters.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `line >= rope.len_lines()`.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use jumprope::*;
+    /// let mut rope = JumpRope::from("fn main() {\n}\n");
+    /// rope.insert_at_line_col(1, 0, "    println!(\"hi\");\n");
+    /// assert_eq!(rope, "fn main() {\n    println!(\"hi\");\n}\n");
+    /// ```
+    pub fn insert_at_line_col(&mut self, line: usize, col: usize, contents: &str) -> usize {
+        assert!(line < self.len_lines(), "Line {line} is out of bounds");
+        let start = self.line_to_char(line);
+        // The end of the line, not including its trailing newline.
+        let end = if line + 1 < self.len_lines() {
+            self.line_to_char(line + 1) - 1
+        } else {
+            self.len_chars()
+        };
+        let pos = start + col.min(end - start);
+        self.insert(pos, contents);
+        pos
+    }
+
+    /// Remove the specified range of lines from the rope, including their trailing newline
+    /// characters.
+    ///
+    /// Any attempt to delete past the last line of the rope will be silently ignored. Note that the
+    /// last line in a document has no trailing newline, so deleting it will leave a trailing
+    /// newline on the previous line.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use jumprope::*;
+    /// let mut rope = JumpRope::from("one\ntwo\nthree");
+    /// rope.remove_line_range(1..2);
+    /// assert_eq!(rope, "one\nthree");
+    /// ```
+    pub fn remove_line_range(&mut self, range: Range<usize>) {
+        let len_lines = self.len_lines();
+        let start = self.line_to_char(range.start.min(len_lines));
+        let end = self.line_to_char(range.end.min(len_lines));
+        self.remove(start..end);
+    }
+}
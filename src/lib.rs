@@ -118,6 +118,11 @@
 //! rope.remove(1..4); // Remove "polar" from our polar bear
 //! assert_eq!(rope, "🐻");
 //! ```
+//!
+//! If you need to find grapheme cluster boundaries (eg, to implement backspace in a text editor),
+//! enable the `graphemes` feature. This adds methods like `next_grapheme_boundary`,
+//! `prev_grapheme_boundary` and `remove_prev_grapheme`, which still use unicode character
+//! positions.
 
 #![cfg_attr(doc_cfg, feature(doc_cfg))]
 
@@ -125,9 +130,42 @@ mod jumprope;
 mod gapbuffer;
 mod utils;
 mod iter;
+mod slice;
+mod search;
+mod io;
+mod snapshot;
+mod anchors;
+mod history;
+mod observers;
+mod frozen;
+mod diff;
+pub mod ot;
+mod batch;
+mod cursor;
+#[cfg(feature = "graphemes")]
+mod graphemes;
+#[cfg(feature = "regex")]
+mod regex;
+#[cfg(feature = "serde")]
+mod serde;
 mod fast_str_tools;
+#[cfg(test)]
+mod test_utils;
 
-pub use crate::jumprope::JumpRope;
+pub use crate::jumprope::{JumpRope, CharBoundaryError};
+#[cfg(feature = "wchar_conversion")]
+pub use crate::jumprope::SurrogatePairError;
+pub use crate::slice::RopeSlice;
+pub use crate::io::{RopeReader, RopeWriter};
+pub use crate::anchors::{Anchor, Bias};
+pub use crate::observers::{EditEvent, ObserverId};
+pub use crate::frozen::{FrozenRope, FrozenSubstrings, FrozenChars};
+pub use crate::diff::{Patch, DEFAULT_DIFF_LIMIT};
+pub use crate::batch::EditOrderError;
+pub use crate::cursor::{EditCursor, RopeCursor};
+#[cfg(feature = "regex")]
+pub use crate::regex::{RopeRegex, RegexBuildError};
 
 mod buffered;
-pub use crate::buffered::JumpRopeBuf;
\ No newline at end of file
+pub use crate::buffered::JumpRopeBuf;
+pub use crate::history::JumpRopeHistory;
\ No newline at end of file
@@ -0,0 +1,279 @@
+use std::fmt::{Debug, Formatter};
+use std::ops::Range;
+use crate::fast_str_tools::count_chars;
+#[cfg(feature = "wchar_conversion")]
+use crate::fast_str_tools::count_utf16_surrogates;
+use crate::jumprope::SkipEntry;
+use crate::frozen::SnapshotMirror;
+use crate::{JumpRope, JumpRopeBuf};
+
+/// A description of a single edit to a rope, passed to observers registered with
+/// [`JumpRope::add_observer`].
+///
+/// Each edit removes a range of the document and inserts some text in its place. All ranges name
+/// positions in the document *before* the edit was applied.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct EditEvent<'a> {
+    /// The range of unicode characters which was removed. This is empty for insertions.
+    pub range: Range<usize>,
+
+    /// The range of bytes (in UTF-8) which was removed.
+    pub byte_range: Range<usize>,
+
+    /// The range of wchars (UTF-16 code units) which was removed.
+    #[cfg(feature = "wchar_conversion")]
+    pub wchar_range: Range<usize>,
+
+    /// The text inserted at the start of the range. This is empty for deletes.
+    pub inserted: &'a str,
+}
+
+impl<'a> EditEvent<'a> {
+    /// Build an event from the position of the edit, and the length of the rope before and after
+    /// the edit.
+    pub(crate) fn new(start: &SkipEntry, before: &SkipEntry, after: &SkipEntry, inserted: &'a str) -> Self {
+        let removed_chars = before.skip_chars + count_chars(inserted) - after.skip_chars;
+        let removed_bytes = before.skip_bytes + inserted.len() - after.skip_bytes;
+
+        EditEvent {
+            range: start.skip_chars..start.skip_chars + removed_chars,
+            byte_range: start.skip_bytes..start.skip_bytes + removed_bytes,
+            #[cfg(feature = "wchar_conversion")]
+            wchar_range: {
+                let removed_pairs = before.skip_pairs + count_utf16_surrogates(inserted) - after.skip_pairs;
+                let start_wchars = start.skip_chars + start.skip_pairs;
+                start_wchars..start_wchars + removed_chars + removed_pairs
+            },
+            inserted,
+        }
+    }
+}
+
+/// A handle naming an observer registered with [`JumpRope::add_observer`]. This can be used to
+/// remove the observer again.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct ObserverId(u64);
+
+type ObserverFn = Box<dyn FnMut(&EditEvent) + Send>;
+
+#[derive(Default)]
+pub(crate) struct Observers {
+    list: Vec<(ObserverId, ObserverFn)>,
+    next_id: u64,
+
+    /// A copy of the rope used for snapshots. This is created by JumpRope::snapshot, and dropped
+    /// again once the snapshots aren't in use.
+    pub(crate) mirror: Option<SnapshotMirror>,
+}
+
+impl Observers {
+    pub(crate) fn is_empty(&self) -> bool {
+        self.list.is_empty() && self.mirror.is_none()
+    }
+
+    pub(crate) fn has_listeners(&self) -> bool {
+        !self.list.is_empty()
+    }
+
+    /// The snapshot mirror which needs to be told about the next edit, if there is one. This drops
+    /// the mirror if it's no longer needed.
+    pub(crate) fn mirror_for_edit(&mut self) -> Option<&mut SnapshotMirror> {
+        if self.mirror.as_mut().is_some_and(|m| !m.keep()) {
+            self.mirror = None;
+        }
+        self.mirror.as_mut()
+    }
+
+    pub(crate) fn notify(&mut self, event: &EditEvent) {
+        if let Some(mirror) = self.mirror_for_edit() {
+            mirror.apply(event);
+        }
+        self.notify_listeners(event);
+    }
+
+    pub(crate) fn notify_listeners(&mut self, event: &EditEvent) {
+        for (_, f) in self.list.iter_mut() {
+            f(event);
+        }
+    }
+}
+
+impl Debug for Observers {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.debug_list()
+            .entries(self.list.iter().map(|(id, _)| id))
+            .finish()
+    }
+}
+
+impl JumpRope {
+    /// Register a function which is called after every edit to the rope, with a description of
+    /// the edit. This includes edits made with `insert`, `remove`, `replace` (and their byte, wchar
+    /// and line variants), as well as edits made through writers.
+    ///
+    /// Observers aren't copied when the rope is cloned.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use jumprope::*;
+    /// use std::sync::{Arc, Mutex};
+    ///
+    /// let mut rope = JumpRope::from("hi there");
+    /// let edits = Arc::new(Mutex::new(vec![]));
+    /// let edits_2 = edits.clone();
+    /// rope.add_observer(move |e| {
+    ///     edits_2.lock().unwrap().push((e.range.clone(), e.inserted.to_string()));
+    /// });
+    ///
+    /// rope.replace(3..8, "you");
+    /// assert_eq!(*edits.lock().unwrap(), vec![(3..8, "you".to_string())]);
+    /// ```
+    pub fn add_observer<F: FnMut(&EditEvent) + Send + 'static>(&mut self, f: F) -> ObserverId {
+        let observers = self.observers.get_or_insert_with(Default::default);
+        let id = ObserverId(observers.next_id);
+        observers.next_id += 1;
+        observers.list.push((id, Box::new(f)));
+        id
+    }
+
+    /// Remove an observer added with [`add_observer`](Self::add_observer). Returns false if the
+    /// observer was not found.
+    pub fn remove_observer(&mut self, id: ObserverId) -> bool {
+        if let Some(observers) = self.observers.as_deref_mut() {
+            let len = observers.list.len();
+            observers.list.retain(|(i, _)| *i != id);
+            observers.list.len() < len
+        } else { false }
+    }
+}
+
+impl JumpRopeBuf {
+    /// Register a function which is called after every edit to the rope. See
+    /// [`JumpRope::add_observer`].
+    ///
+    /// Observers are notified when buffered edits are flushed into the underlying rope. Adjacent
+    /// edits may have been merged together by then.
+    pub fn add_observer<F: FnMut(&EditEvent) + Send + 'static>(&mut self, f: F) -> ObserverId {
+        self.as_mut().add_observer(f)
+    }
+
+    /// Remove an observer added with [`add_observer`](Self::add_observer). Returns false if the
+    /// observer was not found.
+    pub fn remove_observer(&mut self, id: ObserverId) -> bool {
+        self.as_mut().remove_observer(id)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{Arc, Mutex};
+    use std::fmt::Write;
+    use crate::{JumpRope, JumpRopeBuf};
+
+    /// Apply the events to a string copy of the document, checking the byte ranges match.
+    fn mirror(rope: &mut JumpRope, start: &str) -> Arc<Mutex<String>> {
+        let doc = Arc::new(Mutex::new(start.to_string()));
+        let doc_2 = doc.clone();
+        rope.add_observer(move |e| {
+            let mut doc = doc_2.lock().unwrap();
+            let byte_start = doc.char_indices().nth(e.range.start).map_or(doc.len(), |(b, _)| b);
+            assert_eq!(e.byte_range.start, byte_start);
+            #[cfg(feature = "wchar_conversion")] {
+                let wchars = |s: &str| s.chars().map(|c| c.len_utf16()).sum::<usize>();
+                assert_eq!(e.wchar_range.start, wchars(&doc[..byte_start]));
+                assert_eq!(e.wchar_range.len(), wchars(&doc[e.byte_range.clone()]));
+            }
+            assert_eq!(doc[e.byte_range.clone()].chars().count(), e.range.len());
+            doc.replace_range(e.byte_range.clone(), e.inserted);
+        });
+        doc
+    }
+
+    #[test]
+    fn observers_see_edits() {
+        let start = "κό𝕐𝕆😘σμε hi there";
+        let mut rope = JumpRope::from(start);
+        let doc = mirror(&mut rope, start);
+
+        rope.insert(2, "xx𝕐");
+        rope.remove(1..5);
+        rope.replace(3..10, "ab\ncd");
+        rope.insert_at_byte(0, "𝕆").unwrap();
+        write!(rope.writer_at(3), "{} and 𝕆", 1).unwrap();
+        rope.append(JumpRope::from("more text which is long enough to use multiple nodes"));
+        let _rest = rope.split_off(10);
+        rope.remove_returning(2..4);
+
+        assert_eq!(*doc.lock().unwrap(), rope.to_string());
+    }
+
+    /// Count the events fired by each call to f.
+    fn count_events(rope: &mut JumpRope, f: impl FnOnce(&mut JumpRope)) -> usize {
+        let count = Arc::new(Mutex::new(0));
+        let count_2 = count.clone();
+        let id = rope.add_observer(move |_| *count_2.lock().unwrap() += 1);
+        f(rope);
+        rope.remove_observer(id);
+        let count = *count.lock().unwrap();
+        count
+    }
+
+    #[test]
+    fn one_event_per_edit() {
+        let long = "some text which is long enough to span several nodes in the rope";
+        let mut rope = JumpRope::from(long);
+
+        assert_eq!(count_events(&mut rope, |r| r.replace(3..20, "𝕐𝕆 replaced")), 1);
+        assert_eq!(count_events(&mut rope, |r| { r.remove_returning(0..2); }), 1);
+        assert_eq!(count_events(&mut rope, |r| r.append(JumpRope::from(long))), 1);
+        assert_eq!(count_events(&mut rope, |r| { r.split_off(10); }), 1);
+        assert_eq!(count_events(&mut rope, |r| r.apply_edits([(1..2, "a"), (4..4, "b")]).unwrap()), 2);
+
+        // Edits which don't change anything aren't reported.
+        assert_eq!(count_events(&mut rope, |r| r.replace(2..2, "")), 0);
+        assert_eq!(count_events(&mut rope, |r| r.append(JumpRope::new())), 0);
+        let len = rope.len_chars();
+        assert_eq!(count_events(&mut rope, |r| { r.split_off(len); }), 0);
+    }
+
+    #[cfg(feature = "wchar_conversion")]
+    #[test]
+    fn one_event_per_wchar_replace() {
+        let mut rope = JumpRope::from("a𝕐b");
+        let doc = mirror(&mut rope, "a𝕐b");
+        assert_eq!(count_events(&mut rope, |r| r.replace_at_wchar(1..3, "xy")), 1);
+        assert_eq!(*doc.lock().unwrap(), "axyb");
+    }
+
+    #[test]
+    fn removing_observers() {
+        let mut rope = JumpRope::new();
+        assert!(!rope.remove_observer(super::ObserverId(0)));
+        let id = rope.add_observer(|_| {});
+        assert!(rope.remove_observer(id));
+
+        // The ids of removed observers aren't reused.
+        let id_2 = rope.add_observer(|_| {});
+        assert_ne!(id, id_2);
+        assert!(!rope.remove_observer(id));
+        rope.insert(0, "hi");
+    }
+
+    #[test]
+    fn observe_buffered() {
+        let mut rope = JumpRopeBuf::new_from_str("hi there");
+        let doc = Arc::new(Mutex::new(vec![]));
+        let doc_2 = doc.clone();
+        let id = rope.add_observer(move |e| doc_2.lock().unwrap().push(e.inserted.to_string()));
+
+        rope.insert(2, "a");
+        rope.insert(3, "b");
+        assert!(doc.lock().unwrap().is_empty());
+        assert_eq!(rope, "hiab there");
+        assert_eq!(*doc.lock().unwrap(), vec!["ab".to_string()]);
+
+        assert!(rope.remove_observer(id));
+        assert!(!rope.remove_observer(id));
+    }
+}
@@ -0,0 +1,557 @@
+//! Operational transform primitives for plain text.
+//!
+//! An [`Operation`] describes a change to a whole document as a list of components, which are
+//! applied by walking through the document from the start. Each component retains (skips over),
+//! inserts or deletes text. All lengths are counted in unicode characters.
+//!
+//! Operations span the whole document, so any text after the last edit must be retained
+//! explicitly. This lets mistakes be caught early: operations are only ever applied to a document
+//! of their [base length](Operation::base_len), transformed against operations with the same base
+//! length, and composed with operations whose base length is their
+//! [target length](Operation::target_len).
+//!
+//! This is enough to build a basic collaborative editing server: concurrent operations are
+//! transformed against one another with [`Operation::transform`], then applied with
+//! [`JumpRope::apply`].
+//!
+//! # Example
+//!
+//! ```
+//! # use jumprope::*;
+//! use jumprope::ot::{Operation, Side};
+//!
+//! let mut doc = JumpRope::from("hi there");
+//!
+//! // Two users concurrently edit the document.
+//! let a = Operation::new().retain(3).delete(5).insert("you");
+//! let b = Operation::new().insert("oh ").retain(8);
+//!
+//! // Each operation is transformed so it can be applied after the other.
+//! let a2 = a.transform(&b, Side::Left);
+//! let b2 = b.transform(&a, Side::Right);
+//!
+//! let mut doc2 = doc.clone();
+//! doc.apply(&a);
+//! doc.apply(&b2);
+//! doc2.apply(&b);
+//! doc2.apply(&a2);
+//! assert_eq!(doc, "oh hi you");
+//! assert_eq!(doc, doc2);
+//! ```
+
+use crate::fast_str_tools::{char_to_byte_idx, count_chars};
+use crate::JumpRope;
+
+/// A single component of an [`Operation`].
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum Component {
+    /// Skip over the specified number of characters, leaving them unchanged.
+    Retain(usize),
+    /// Insert text at the current position.
+    Insert(String),
+    /// Delete the specified number of characters at the current position.
+    Delete(usize),
+}
+
+/// When two operations insert text at the same position, the side decides which insert ends up
+/// first in the document. Operations being transformed against each other must be passed opposite
+/// sides.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Side {
+    /// The operation being transformed wins ties. Its inserts go first.
+    Left,
+    /// The other operation wins ties.
+    Right,
+}
+
+/// An edit to a text document. See the [module level documentation](self) for details.
+///
+/// Operations are built up using [`retain`](Self::retain), [`insert`](Self::insert) and
+/// [`delete`](Self::delete). Adjacent components of the same type are merged together, and when
+/// an insert and a delete happen at the same position, the insert is stored first.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Default)]
+pub struct Operation {
+    components: Vec<Component>,
+}
+
+impl Operation {
+    /// Create an empty operation, which doesn't change the document.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add a retain component to the operation.
+    pub fn retain(mut self, n: usize) -> Self {
+        self.push_retain(n);
+        self
+    }
+
+    /// Add an insert component to the operation.
+    pub fn insert(mut self, content: &str) -> Self {
+        self.push_insert(content);
+        self
+    }
+
+    /// Add a delete component to the operation.
+    pub fn delete(mut self, n: usize) -> Self {
+        self.push_delete(n);
+        self
+    }
+
+    /// The components making up the operation.
+    pub fn components(&self) -> &[Component] {
+        &self.components
+    }
+
+    /// Returns true if the operation doesn't change the document.
+    pub fn is_noop(&self) -> bool {
+        self.components.iter().all(|c| matches!(c, Component::Retain(_)))
+    }
+
+    /// The length (in chars) of the document this operation applies to.
+    pub fn base_len(&self) -> usize {
+        self.components.iter().map(|c| match c {
+            Component::Retain(n) | Component::Delete(n) => *n,
+            Component::Insert(_) => 0,
+        }).sum()
+    }
+
+    /// The length (in chars) of the document after this operation has been applied.
+    pub fn target_len(&self) -> usize {
+        self.components.iter().map(|c| match c {
+            Component::Retain(n) => *n,
+            Component::Insert(s) => count_chars(s),
+            Component::Delete(_) => 0,
+        }).sum()
+    }
+
+    fn push_retain(&mut self, n: usize) {
+        if n == 0 { return; }
+        if let Some(Component::Retain(last)) = self.components.last_mut() {
+            *last += n;
+        } else {
+            self.components.push(Component::Retain(n));
+        }
+    }
+
+    fn push_insert(&mut self, content: &str) {
+        if content.is_empty() { return; }
+        let len = self.components.len();
+
+        // Inserts are kept before deletes at the same position.
+        let idx = match self.components.last() {
+            Some(Component::Delete(_)) => len - 1,
+            _ => len,
+        };
+        if idx > 0 {
+            if let Component::Insert(s) = &mut self.components[idx - 1] {
+                s.push_str(content);
+                return;
+            }
+        }
+        self.components.insert(idx, Component::Insert(content.into()));
+    }
+
+    fn push_delete(&mut self, n: usize) {
+        if n == 0 { return; }
+        if let Some(Component::Delete(last)) = self.components.last_mut() {
+            *last += n;
+        } else {
+            self.components.push(Component::Delete(n));
+        }
+    }
+
+    /// Merge this operation with `other`, which happens immediately afterwards. The result has the
+    /// same effect as applying this operation then `other`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the base length of `other` doesn't match the target length of this operation.
+    pub fn compose(&self, other: &Operation) -> Operation {
+        assert_eq!(self.target_len(), other.base_len(), "Composed operations don't line up");
+
+        let mut result = Operation::new();
+        let mut a = ComponentIter::new(self);
+        let mut b = ComponentIter::new(other);
+
+        loop {
+            match (a.peek(), b.peek()) {
+                (_, Piece::Insert(s)) => {
+                    result.push_insert(s);
+                    b.take_all();
+                }
+                (Piece::Delete(n), _) => {
+                    result.push_delete(n);
+                    a.take_all();
+                }
+                (Piece::End, Piece::End) => break,
+                (pa, pb) => {
+                    let n = pa.len().min(pb.len());
+                    match (pa, pb) {
+                        (Piece::Insert(s), Piece::Retain(_) | Piece::End) => {
+                            result.push_insert(&s[..char_to_byte_idx(s, n)]);
+                        }
+                        (Piece::Insert(_), Piece::Delete(_)) => {} // The insert is deleted again.
+                        (_, Piece::Delete(_)) => result.push_delete(n),
+                        _ => result.push_retain(n),
+                    }
+                    a.take(n);
+                    b.take(n);
+                }
+            }
+        }
+
+        result
+    }
+
+    /// Transform this operation so it can be applied after `other`, where both operations were
+    /// made concurrently against the same document.
+    ///
+    /// `side` breaks ties when both operations insert at the same position. If this operation is
+    /// transformed with `Side::Left`, `other` must be transformed against it with `Side::Right`
+    /// (and vice versa) for the results to converge.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the operations have different base lengths.
+    pub fn transform(&self, other: &Operation, side: Side) -> Operation {
+        assert_eq!(self.base_len(), other.base_len(), "Operations have different base lengths");
+
+        let mut result = Operation::new();
+        let mut a = ComponentIter::new(self);
+        let mut b = ComponentIter::new(other);
+
+        loop {
+            match (a.peek(), b.peek()) {
+                (Piece::Insert(s), pb) if side == Side::Left || !matches!(pb, Piece::Insert(_)) => {
+                    result.push_insert(s);
+                    a.take_all();
+                }
+                (_, Piece::Insert(s)) => {
+                    result.push_retain(count_chars(s));
+                    b.take_all();
+                }
+                (Piece::End, Piece::End) => break,
+                (pa, pb) => {
+                    let n = pa.len().min(pb.len());
+                    match (pa, pb) {
+                        // Text deleted by other is skipped.
+                        (_, Piece::Delete(_)) => {}
+                        (Piece::Delete(_), _) => result.push_delete(n),
+                        _ => result.push_retain(n),
+                    }
+                    a.take(n);
+                    b.take(n);
+                }
+            }
+        }
+
+        result
+    }
+
+    /// Create the inverse of this operation, which undoes it. `doc` is the document the operation
+    /// is applied to (before it was applied), which is needed to restore any deleted text.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the length of `doc` doesn't match the operation's base length.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use jumprope::*;
+    /// use jumprope::ot::Operation;
+    ///
+    /// let mut doc = JumpRope::from("hi there");
+    /// let op = Operation::new().retain(2).delete(6).insert("!");
+    /// let inverse = op.invert(&doc);
+    ///
+    /// doc.apply(&op);
+    /// assert_eq!(doc, "hi!");
+    /// doc.apply(&inverse);
+    /// assert_eq!(doc, "hi there");
+    /// ```
+    pub fn invert(&self, doc: &JumpRope) -> Operation {
+        assert_eq!(self.base_len(), doc.len_chars(), "Operation doesn't match the document");
+
+        let mut result = Operation::new();
+        let mut pos = 0;
+        for c in &self.components {
+            match c {
+                Component::Retain(n) => {
+                    result.push_retain(*n);
+                    pos += n;
+                }
+                Component::Insert(s) => result.push_delete(count_chars(s)),
+                Component::Delete(n) => {
+                    for s in doc.slice_substrings(pos..pos + n) {
+                        result.push_insert(s);
+                    }
+                    pos += n;
+                }
+            }
+        }
+        result
+    }
+}
+
+/// The remainder of the current component of an operation.
+#[derive(Debug, Clone, Copy)]
+enum Piece<'a> {
+    Retain(usize),
+    Insert(&'a str),
+    Delete(usize),
+    /// The end of the operation.
+    End,
+}
+
+impl<'a> Piece<'a> {
+    fn len(&self) -> usize {
+        match self {
+            Piece::Retain(n) | Piece::Delete(n) => *n,
+            Piece::Insert(s) => count_chars(s),
+            Piece::End => usize::MAX,
+        }
+    }
+}
+
+/// Walks through the components of an operation, splitting them as needed.
+struct ComponentIter<'a> {
+    components: &'a [Component],
+    idx: usize,
+    /// How far into the current component we are. This is in bytes for inserts, and chars
+    /// otherwise.
+    offset: usize,
+}
+
+impl<'a> ComponentIter<'a> {
+    fn new(op: &'a Operation) -> Self {
+        Self { components: &op.components, idx: 0, offset: 0 }
+    }
+
+    fn peek(&self) -> Piece<'a> {
+        match self.components.get(self.idx) {
+            None => Piece::End,
+            Some(Component::Retain(n)) => Piece::Retain(n - self.offset),
+            Some(Component::Delete(n)) => Piece::Delete(n - self.offset),
+            Some(Component::Insert(s)) => Piece::Insert(&s[self.offset..]),
+        }
+    }
+
+    fn take_all(&mut self) {
+        self.idx += 1;
+        self.offset = 0;
+    }
+
+    /// Consume n chars of the current component.
+    fn take(&mut self, n: usize) {
+        match self.peek() {
+            Piece::End => {}
+            Piece::Insert(s) => {
+                if n == count_chars(s) { self.take_all(); } else {
+                    self.offset += char_to_byte_idx(s, n);
+                }
+            }
+            p => {
+                if n == p.len() { self.take_all(); } else {
+                    self.offset += n;
+                }
+            }
+        }
+    }
+}
+
+impl JumpRope {
+    /// Apply an operation to the rope. The operation is applied in a single pass through the
+    /// rope, using one cursor.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the operation's base length doesn't match the length of the rope.
+    pub fn apply(&mut self, op: &Operation) {
+        assert_eq!(op.base_len(), self.len_chars(), "Operation doesn't match the rope");
+
+        // There's no need to walk the cursor over the retained text at the end.
+        let components = match op.components.split_last() {
+            Some((Component::Retain(_), rest)) => rest,
+            _ => &op.components[..],
+        };
+
+        let mut cursor = self.mut_cursor_at_char(0, true);
+        for c in components {
+            match c {
+                Component::Retain(n) => cursor.advance_by_chars(*n),
+                Component::Insert(s) => Self::insert_at_cursor(&mut cursor, s),
+                Component::Delete(n) => Self::del_at_cursor(&mut cursor, *n, None),
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::prelude::*;
+    use crate::JumpRope;
+    use crate::test_utils::{random_content, SAMPLE};
+    use super::*;
+
+    fn random_op(rng: &mut SmallRng, doc_len: usize) -> Operation {
+        let mut op = Operation::new();
+        let mut remaining = doc_len;
+        while remaining > 0 && rng.gen_bool(0.8) {
+            let n = rng.gen_range(1..=remaining.min(20));
+            match rng.gen_range(0..3) {
+                0 => op = op.retain(n),
+                1 => op = op.delete(n),
+                _ => {
+                    op = op.insert(random_content(rng));
+                    continue;
+                }
+            }
+            remaining -= n;
+        }
+        op.retain(remaining)
+    }
+
+    fn applied(doc: &JumpRope, op: &Operation) -> JumpRope {
+        let mut doc = doc.clone();
+        doc.apply(op);
+        doc.check();
+        doc
+    }
+
+    #[test]
+    fn builder_normalizes() {
+        let op = Operation::new().retain(1).retain(2).delete(1).insert("a").delete(2).insert("b");
+        assert_eq!(op.components(), &[
+            Component::Retain(3),
+            Component::Insert("ab".into()),
+            Component::Delete(3),
+        ]);
+        assert_eq!(op.base_len(), 6);
+        assert_eq!(op.target_len(), 5);
+        assert!(Operation::new().retain(10).is_noop());
+    }
+
+    #[test]
+    fn apply_op() {
+        let mut doc = JumpRope::from(SAMPLE);
+        let op = Operation::new().retain(2).delete(2).insert("xx").retain(30).insert("!").delete(3).retain(23);
+        doc.apply(&op);
+        doc.check();
+        assert_eq!(doc, "κόxx😘σμε hi there\nthis is long eno! to span several nodes\n");
+    }
+
+    #[test]
+    fn empty_document() {
+        let empty = JumpRope::new();
+        let noop = Operation::new();
+        assert_eq!(noop.base_len(), 0);
+        assert!(noop.is_noop());
+        assert_eq!(applied(&empty, &noop), "");
+        assert_eq!(noop.invert(&empty), noop);
+
+        let ins = Operation::new().insert("κόσμε");
+        assert_eq!(ins.base_len(), 0);
+        assert_eq!(applied(&empty, &ins), "κόσμε");
+        assert_eq!(noop.compose(&ins), ins);
+        assert_eq!(ins.transform(&noop, Side::Left), ins);
+        assert_eq!(noop.transform(&ins, Side::Left), Operation::new().retain(5));
+
+        let after = applied(&empty, &ins);
+        assert_eq!(applied(&after, &ins.invert(&empty)), "");
+    }
+
+    #[test]
+    fn edits_at_the_end() {
+        let doc = JumpRope::from("κόσμε");
+        let append = Operation::new().retain(5).insert("!");
+        let clear = Operation::new().delete(5);
+        assert_eq!(applied(&doc, &append), "κόσμε!");
+        assert_eq!(applied(&doc, &clear), "");
+
+        // Appending survives the whole document being deleted.
+        assert_eq!(append.transform(&clear, Side::Left), Operation::new().insert("!"));
+        assert_eq!(applied(&applied(&doc, &clear), &append.transform(&clear, Side::Left)), "!");
+        assert_eq!(append.compose(&Operation::new().delete(6)), clear);
+        assert_eq!(clear.invert(&doc), Operation::new().insert("κόσμε"));
+    }
+
+    #[test]
+    fn insert_tie_break() {
+        let doc = JumpRope::from("ab");
+        let a = Operation::new().retain(1).insert("A").retain(1);
+        let b = Operation::new().retain(1).insert("B").retain(1);
+
+        assert_eq!(applied(&applied(&doc, &b), &a.transform(&b, Side::Left)), "aABb");
+        assert_eq!(applied(&applied(&doc, &a), &b.transform(&a, Side::Right)), "aABb");
+    }
+
+    #[test]
+    #[should_panic]
+    fn apply_wrong_length() {
+        JumpRope::from("abc").apply(&Operation::new().retain(2));
+    }
+
+    #[test]
+    #[should_panic]
+    fn transform_mismatched() {
+        let a = Operation::new().retain(2).insert("x");
+        let b = Operation::new().delete(3);
+        a.transform(&b, Side::Left);
+    }
+
+    #[test]
+    #[should_panic]
+    fn compose_mismatched() {
+        let a = Operation::new().retain(2).insert("x");
+        let b = Operation::new().retain(2).delete(2);
+        a.compose(&b);
+    }
+
+    #[test]
+    #[should_panic]
+    fn invert_mismatched() {
+        Operation::new().delete(2).invert(&JumpRope::from("abc"));
+    }
+
+    #[test]
+    fn random_ops() {
+        let mut rng = SmallRng::seed_from_u64(21);
+        for _i in 0..300 {
+            let doc = JumpRope::from(SAMPLE);
+            let a = random_op(&mut rng, doc.len_chars());
+            let b = random_op(&mut rng, doc.len_chars());
+
+            // Apply should match replaying the components as edits.
+            let after_a = applied(&doc, &a);
+            let mut expected = doc.clone();
+            let mut pos = 0;
+            for c in a.components() {
+                match c {
+                    Component::Retain(n) => pos += n,
+                    Component::Insert(s) => {
+                        expected.insert(pos, s);
+                        pos += count_chars(s);
+                    }
+                    Component::Delete(n) => expected.remove(pos..pos + n),
+                }
+            }
+            assert_eq!(after_a, expected);
+
+            // Inverting undoes the operation.
+            assert_eq!(applied(&after_a, &a.invert(&doc)), doc);
+
+            // Transformed operations converge.
+            let a2 = a.transform(&b, Side::Left);
+            let b2 = b.transform(&a, Side::Right);
+            let left = applied(&after_a, &b2);
+            let right = applied(&applied(&doc, &b), &a2);
+            assert_eq!(left, right);
+
+            // Composing gives the same result as applying both.
+            let c = random_op(&mut rng, after_a.len_chars());
+            assert_eq!(applied(&doc, &a.compose(&c)), applied(&after_a, &c));
+        }
+    }
+}
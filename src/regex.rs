@@ -0,0 +1,330 @@
+use std::ops::Range;
+use regex_automata::hybrid::dfa::{Cache, DFA};
+use regex_automata::nfa::thompson;
+use regex_automata::util::start;
+use regex_automata::{Anchored, MatchKind};
+use crate::JumpRope;
+
+/// The error returned when a regular expression fails to compile.
+pub use regex_automata::hybrid::BuildError as RegexBuildError;
+
+/// A compiled regular expression which can be used to search a [`JumpRope`].
+///
+/// Searching is done using a lazy DFA (from the
+/// [regex-automata](https://crates.io/crates/regex-automata) crate) which is fed the rope's
+/// contents one chunk at a time. The rope's contents are never copied into a string.
+///
+/// Matches use the same leftmost-first semantics as the `regex` crate. Note that Unicode word
+/// boundaries (`\b`) aren't supported by the lazy DFA. Use an ASCII word boundary (`(?-u:\b)`)
+/// instead.
+///
+/// # Example
+///
+/// ```
+/// # use jumprope::*;
+/// let rope = JumpRope::from("The year 1984 came after 1983.");
+/// let re = RopeRegex::new(r"[0-9]{4}").unwrap();
+/// assert_eq!(rope.find_regex(&re), Some(9..13));
+/// assert_eq!(rope.regex_matches(&re).collect::<Vec<_>>(), vec![9..13, 25..29]);
+/// ```
+#[derive(Clone, Debug)]
+pub struct RopeRegex {
+    forward: DFA,
+    /// Used to find the start of each match, by searching backwards from the end of the match.
+    reverse: DFA,
+}
+
+impl RopeRegex {
+    /// Compile a new regular expression.
+    #[allow(clippy::result_large_err)]
+    pub fn new(pattern: &str) -> Result<Self, RegexBuildError> {
+        let forward = DFA::new(pattern)?;
+        let reverse = DFA::builder()
+            .configure(DFA::config().prefilter(None).match_kind(MatchKind::All))
+            .thompson(thompson::Config::new().reverse(true))
+            .build(pattern)?;
+
+        Ok(Self { forward, reverse })
+    }
+}
+
+/// Iterator over the (non-overlapping) matches of a regular expression in a rope. Each match is
+/// yielded as a range of unicode character offsets.
+pub struct RegexMatches<'a, 'r> {
+    rope: &'a JumpRope,
+    regex: &'r RopeRegex,
+    forward_cache: Cache,
+    reverse_cache: Cache,
+
+    /// The byte offset to start the next search from.
+    pos: usize,
+    /// The byte offset of the end of the searched range.
+    end: usize,
+    last_match_end: Option<usize>,
+    done: bool,
+}
+
+impl<'a, 'r> Iterator for RegexMatches<'a, 'r> {
+    type Item = Range<usize>;
+
+    fn next(&mut self) -> Option<Range<usize>> {
+        while !self.done {
+            let Some(m) = self.rope.find_regex_bytes(self.regex, &mut self.forward_cache, &mut self.reverse_cache, self.pos, self.end) else {
+                self.done = true;
+                break;
+            };
+
+            if m.is_empty() && Some(m.end) == self.last_match_end {
+                // Empty matches aren't allowed at the end of the previous match. Skip forward a
+                // character and search again.
+                if m.end == self.end {
+                    self.done = true;
+                } else {
+                    let (chunk, chunk_start) = self.rope.chunk_at_byte(m.end);
+                    let c = chunk[m.end - chunk_start..].chars().next().unwrap();
+                    self.pos = m.end + c.len_utf8();
+                }
+                continue;
+            }
+
+            self.pos = m.end;
+            self.last_match_end = Some(m.end);
+            return Some(self.rope.byte_to_char(m.start).unwrap()..self.rope.byte_to_char(m.end).unwrap());
+        }
+
+        None
+    }
+}
+
+impl JumpRope {
+    fn byte_at(&self, byte_pos: usize) -> u8 {
+        let (chunk, chunk_start) = self.chunk_at_byte(byte_pos);
+        chunk.as_bytes()[byte_pos - chunk_start]
+    }
+
+    /// Find the first match of the regex in the byte range start..end, returning its byte range.
+    /// Text outside the range is used as context for look-around assertions (like `^` and `\b`).
+    fn find_regex_bytes(&self, regex: &RopeRegex, forward_cache: &mut Cache, reverse_cache: &mut Cache, start: usize, end: usize) -> Option<Range<usize>> {
+        let len = self.len_bytes();
+
+        // First search forwards to find the end of the match.
+        let dfa = &regex.forward;
+        let look_behind = if start > 0 { Some(self.byte_at(start - 1)) } else { None };
+        let config = start::Config::new().anchored(Anchored::No).look_behind(look_behind);
+        let mut sid = dfa.start_state(forward_cache, &config)
+            .expect("Lazy DFA failed to compute start state");
+
+        // Matches in the DFA are delayed by 1 byte. When we enter a match state after consuming
+        // the byte at pos, the match ended at pos.
+        let mut match_end = None;
+        let mut pos = start;
+        let mut dead = false;
+        'outer: while pos < end {
+            let (chunk, chunk_start) = self.chunk_at_byte(pos);
+            let bytes = &chunk.as_bytes()[pos - chunk_start..(end - chunk_start).min(chunk.len())];
+            for &b in bytes {
+                sid = dfa.next_state(forward_cache, sid, b).expect("Lazy DFA gave up");
+                if sid.is_match() {
+                    match_end = Some(pos);
+                } else if sid.is_dead() {
+                    dead = true;
+                    break 'outer;
+                }
+                pos += 1;
+            }
+        }
+
+        if !dead {
+            sid = if end < len {
+                dfa.next_state(forward_cache, sid, self.byte_at(end))
+            } else {
+                dfa.next_eoi_state(forward_cache, sid)
+            }.expect("Lazy DFA gave up");
+            if sid.is_match() { match_end = Some(end); }
+        }
+
+        let match_end = match_end?;
+
+        // Then search backwards from the end of the match to find where it starts.
+        let dfa = &regex.reverse;
+        let look_behind = if match_end < len { Some(self.byte_at(match_end)) } else { None };
+        let config = start::Config::new().anchored(Anchored::Yes).look_behind(look_behind);
+        let mut sid = dfa.start_state(reverse_cache, &config)
+            .expect("Lazy DFA failed to compute start state");
+
+        let mut match_start = None;
+        let mut pos = match_end;
+        let mut dead = false;
+        'outer: while pos > start {
+            let (chunk, chunk_start) = self.chunk_at_byte(pos - 1);
+            let bytes = &chunk.as_bytes()[start.max(chunk_start) - chunk_start..pos - chunk_start];
+            for &b in bytes.iter().rev() {
+                sid = dfa.next_state(reverse_cache, sid, b).expect("Lazy DFA gave up");
+                if sid.is_match() {
+                    match_start = Some(pos);
+                } else if sid.is_dead() {
+                    dead = true;
+                    break 'outer;
+                }
+                pos -= 1;
+            }
+        }
+
+        if !dead {
+            sid = if start > 0 {
+                dfa.next_state(reverse_cache, sid, self.byte_at(start - 1))
+            } else {
+                dfa.next_eoi_state(reverse_cache, sid)
+            }.expect("Lazy DFA gave up");
+            if sid.is_match() { match_start = Some(start); }
+        }
+
+        let match_start = match_start.expect("Reverse search failed to find the start of a match");
+        Some(match_start..match_end)
+    }
+}
+
+/// These methods are only available if the `regex` feature is enabled.
+///
+/// All positions are specified in unicode characters.
+#[cfg_attr(doc_cfg, doc(cfg(feature = "regex")))]
+impl JumpRope {
+    /// Iterate over all the (non-overlapping) matches of the regular expression in the specified
+    /// range of the rope. Each match is yielded as a range of unicode character offsets.
+    ///
+    /// Text outside the range is still used when checking look-around assertions like `^` and
+    /// `(?-u:\b)`, but matches will never extend outside of the range.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the range is reversed or extends past the end of the rope.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use jumprope::*;
+    /// let rope = JumpRope::from("aaa bbb ccc");
+    /// let re = RopeRegex::new(r"[a-z]+").unwrap();
+    /// assert_eq!(rope.regex_matches_in(&re, 1..9).collect::<Vec<_>>(), vec![1..3, 4..7, 8..9]);
+    /// ```
+    pub fn regex_matches_in<'a, 'r>(&'a self, regex: &'r RopeRegex, range: Range<usize>) -> RegexMatches<'a, 'r> {
+        assert!(range.start <= range.end, "Search range is reversed");
+        assert!(range.end <= self.len_chars(), "Search range extends past the end of the rope");
+
+        RegexMatches {
+            rope: self,
+            regex,
+            forward_cache: regex.forward.create_cache(),
+            reverse_cache: regex.reverse.create_cache(),
+            pos: self.char_to_byte(range.start),
+            end: self.char_to_byte(range.end),
+            last_match_end: None,
+            done: false,
+        }
+    }
+
+    /// Iterate over all the (non-overlapping) matches of the regular expression in the rope.
+    /// Each match is yielded as a range of unicode character offsets.
+    pub fn regex_matches<'a, 'r>(&'a self, regex: &'r RopeRegex) -> RegexMatches<'a, 'r> {
+        self.regex_matches_in(regex, 0..self.len_chars())
+    }
+
+    /// Find the first match of the regular expression in the rope.
+    pub fn find_regex(&self, regex: &RopeRegex) -> Option<Range<usize>> {
+        self.regex_matches(regex).next()
+    }
+
+    /// Find the first match of the regular expression in the rope which starts at or after
+    /// `char_pos`. This is useful for implementing "find next" in an editor. The text before
+    /// `char_pos` is still used when checking look-behind assertions like `^`.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use jumprope::*;
+    /// let rope = JumpRope::from("abc abc");
+    /// let re = RopeRegex::new(r"^abc").unwrap();
+    /// assert_eq!(rope.find_regex_from(&re, 0), Some(0..3));
+    /// assert_eq!(rope.find_regex_from(&re, 1), None);
+    /// ```
+    pub fn find_regex_from(&self, regex: &RopeRegex, char_pos: usize) -> Option<Range<usize>> {
+        self.regex_matches_in(regex, char_pos..self.len_chars()).next()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use regex::Regex;
+    use crate::{JumpRope, RopeRegex};
+    use crate::test_utils::rope_with_gaps;
+
+    fn char_range(s: &str, range: std::ops::Range<usize>) -> std::ops::Range<usize> {
+        s[..range.start].chars().count()..s[..range.end].chars().count()
+    }
+
+    fn check_regex(s: &str, pattern: &str) {
+        let rope = rope_with_gaps(s, 4);
+
+        let re = RopeRegex::new(pattern).unwrap();
+        let expected_re = Regex::new(pattern).unwrap();
+
+        let expected = expected_re.find_iter(s)
+            .map(|m| char_range(s, m.range()))
+            .collect::<Vec<_>>();
+        assert_eq!(rope.regex_matches(&re).collect::<Vec<_>>(), expected, "pattern {:?}", pattern);
+        assert_eq!(rope.find_regex(&re), expected.first().cloned());
+    }
+
+    #[test]
+    fn regex_smoke_tests() {
+        let s = "hello world! κόσμε 𐆚𐆚 line one\nline two\n\nthe end 1984 1983";
+        for pattern in [r"o", r"l+", r"[a-z]+", r"κό.με", r"𐆚+", r"(?m)^line", r"(?m)$", r"",
+            r"a*", r"[0-9]{4}", r"(?-u:\b)[a-z]+(?-u:\b)", r"\s+", r"nope"] {
+            check_regex(s, pattern);
+        }
+        check_regex("", "");
+        check_regex("", "a*");
+    }
+
+    #[test]
+    fn regex_in_range() {
+        let rope = JumpRope::from("aaa bbb ccc");
+        let re = RopeRegex::new(r"(?-u:\b)[a-z]+").unwrap();
+        // The word boundary check looks at the character before the range.
+        assert_eq!(rope.regex_matches_in(&re, 1..9).collect::<Vec<_>>(), vec![4..7, 8..9]);
+        assert_eq!(rope.find_regex_from(&re, 5), Some(8..11));
+
+        // Empty ranges, including at the end of the rope.
+        assert_eq!(rope.regex_matches_in(&re, 5..5).count(), 0);
+        assert_eq!(rope.find_regex_from(&re, 11), None);
+        let empty = RopeRegex::new(r"").unwrap();
+        assert_eq!(rope.regex_matches_in(&empty, 11..11).collect::<Vec<_>>(), vec![11..11]);
+        assert_eq!(JumpRope::new().find_regex(&empty), Some(0..0));
+    }
+
+    #[test]
+    fn regex_across_chunks() {
+        // Put a multi-byte match over each node boundary in turn.
+        let s = "x".repeat(60);
+        let re = RopeRegex::new(r"κό+σ").unwrap();
+        for pos in 0..s.len() - 4 {
+            let mut rope = rope_with_gaps(&s, 7);
+            rope.replace(pos..pos + 4, "κόόσ");
+            assert_eq!(rope.find_regex(&re), Some(pos..pos + 4));
+            assert_eq!(rope.find_regex_from(&re, pos + 1), None);
+        }
+    }
+
+    #[test]
+    fn regex_errors() {
+        assert!(RopeRegex::new(r"(unclosed").is_err());
+        assert!(RopeRegex::new(r"[z-a]").is_err());
+    }
+
+    #[test]
+    #[should_panic]
+    fn regex_range_past_end() {
+        let rope = JumpRope::from("abc");
+        rope.regex_matches_in(&RopeRegex::new("a").unwrap(), 1..4);
+    }
+}
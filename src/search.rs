@@ -0,0 +1,238 @@
+use std::ops::RangeInclusive;
+use crate::fast_str_tools::count_chars;
+use crate::iter::SliceIter;
+use crate::JumpRope;
+
+/// Iterator over the (non-overlapping) positions of a pattern in a rope. The positions are
+/// returned as unicode character offsets.
+///
+/// Matches are found by scanning through the rope's chunks in order. Only the current chunk and
+/// the tail of the previous chunk (enough to find matches spanning the chunk boundary) are kept
+/// in memory at a time.
+pub struct MatchIndices<'a, 'p> {
+    chunks: SliceIter<'a>,
+    pattern: &'p str,
+
+    /// The text being searched. This contains the tail end of the previous chunk (which is always
+    /// too short to contain a match on its own), followed by the current chunk.
+    window: String,
+    /// The byte offset in window to continue searching from.
+    search_pos: usize,
+
+    /// The char position in the rope of window[counted_bytes..].
+    counted_bytes: usize,
+    counted_chars: usize,
+
+    /// An empty pattern matches at every character position. This is handled separately.
+    empty_matches: Option<RangeInclusive<usize>>,
+}
+
+impl<'a, 'p> Iterator for MatchIndices<'a, 'p> {
+    type Item = usize;
+
+    fn next(&mut self) -> Option<usize> {
+        if let Some(range) = self.empty_matches.as_mut() {
+            return range.next();
+        }
+
+        loop {
+            if let Some(p) = self.window[self.search_pos..].find(self.pattern) {
+                let p = self.search_pos + p;
+                self.counted_chars += count_chars(&self.window[self.counted_bytes..p]);
+                self.counted_bytes = p;
+                self.search_pos = p + self.pattern.len();
+                return Some(self.counted_chars);
+            }
+
+            let (chunk, _) = self.chunks.next()?;
+
+            // Keep the end of the window around in case a match spans into the next chunk. (But
+            // don't keep anything before the end of the previous match.)
+            let mut keep_from = self.window.len()
+                .saturating_sub(self.pattern.len() - 1)
+                .max(self.search_pos);
+            while !self.window.is_char_boundary(keep_from) { keep_from += 1; }
+
+            self.counted_chars += count_chars(&self.window[self.counted_bytes..keep_from]);
+            self.window.drain(..keep_from);
+            self.window.push_str(chunk);
+            self.search_pos = 0;
+            self.counted_bytes = 0;
+        }
+    }
+}
+
+impl JumpRope {
+    /// Iterate over the unicode character positions of all (non-overlapping) occurrences of
+    /// `pattern` in the rope. Like [`str::match_indices`], an empty pattern matches at every
+    /// character position.
+    ///
+    /// Matches are found even if they span multiple internal chunks of the rope, and the rope's
+    /// contents are never copied into a single string.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use jumprope::*;
+    /// let rope = JumpRope::from("κόσμε κόσμε");
+    /// assert_eq!(rope.match_indices("σμ").collect::<Vec<_>>(), vec![2, 8]);
+    /// ```
+    pub fn match_indices<'a, 'p>(&'a self, pattern: &'p str) -> MatchIndices<'a, 'p> {
+        self.match_indices_from(pattern, 0)
+    }
+
+    fn match_indices_from<'a, 'p>(&'a self, pattern: &'p str, char_pos: usize) -> MatchIndices<'a, 'p> {
+        let len = self.len_chars();
+        assert!(char_pos <= len);
+
+        MatchIndices {
+            chunks: self.slice_substrings_with_len(char_pos..len),
+            pattern,
+            window: String::new(),
+            search_pos: 0,
+            counted_bytes: 0,
+            counted_chars: char_pos,
+            empty_matches: if pattern.is_empty() { Some(char_pos..=len) } else { None },
+        }
+    }
+
+    /// Find the unicode character position of the first occurrence of `pattern` in the rope.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use jumprope::*;
+    /// let rope = JumpRope::from("κόσμε κόσμε");
+    /// assert_eq!(rope.find("σμ"), Some(2));
+    /// assert_eq!(rope.find("xyz"), None);
+    /// ```
+    pub fn find(&self, pattern: &str) -> Option<usize> {
+        self.find_from(pattern, 0)
+    }
+
+    /// Find the unicode character position of the first occurrence of `pattern` in the rope
+    /// which starts at or after `char_pos`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `char_pos` is past the end of the rope.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use jumprope::*;
+    /// let rope = JumpRope::from("κόσμε κόσμε");
+    /// assert_eq!(rope.find_from("σμ", 3), Some(8));
+    /// ```
+    pub fn find_from(&self, pattern: &str, char_pos: usize) -> Option<usize> {
+        self.match_indices_from(pattern, char_pos).next()
+    }
+
+    /// Find the unicode character position of the last occurrence of `pattern` in the rope.
+    ///
+    /// This searches backwards from the end of the rope (like
+    /// [`substrings_rev`](JumpRope::substrings_rev)), so the cost is proportional to the distance
+    /// between the match and the end of the rope.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use jumprope::*;
+    /// let rope = JumpRope::from("κόσμε κόσμε");
+    /// assert_eq!(rope.rfind("σμ"), Some(8));
+    /// ```
+    pub fn rfind(&self, pattern: &str) -> Option<usize> {
+        let len = self.len_chars();
+        if pattern.is_empty() { return Some(len); }
+
+        // The window contains the current chunk, followed by the start of the next chunk (which
+        // is always too short to contain a match on its own).
+        let mut window = String::new();
+        let mut pos = len; // The char position of the start of window.
+
+        for (chunk, chunk_chars) in self.slice_substrings_with_len(0..len).rev() {
+            let mut keep_to = window.len().min(pattern.len() - 1);
+            while !window.is_char_boundary(keep_to) { keep_to -= 1; }
+
+            window.truncate(keep_to);
+            window.insert_str(0, chunk);
+            pos -= chunk_chars;
+
+            if let Some(p) = window.rfind(pattern) {
+                return Some(pos + count_chars(&window[..p]));
+            }
+        }
+
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::prelude::*;
+    use crate::JumpRope;
+    use crate::test_utils::rope_with_gaps;
+
+    fn char_pos(s: &str, byte_pos: usize) -> usize {
+        s[..byte_pos].chars().count()
+    }
+
+    fn check_search(rope: &JumpRope, s: &str, pattern: &str) {
+        let expected = s.match_indices(pattern).map(|(b, _)| char_pos(s, b)).collect::<Vec<_>>();
+        assert_eq!(rope.match_indices(pattern).collect::<Vec<_>>(), expected);
+
+        assert_eq!(rope.find(pattern), s.find(pattern).map(|b| char_pos(s, b)));
+        assert_eq!(rope.rfind(pattern), s.rfind(pattern).map(|b| char_pos(s, b)));
+
+        for (i, (b, _)) in s.char_indices().enumerate() {
+            let expected = s[b..].find(pattern).map(|m| char_pos(s, b + m));
+            assert_eq!(rope.find_from(pattern, i), expected);
+        }
+    }
+
+    #[test]
+    fn search_smoke_tests() {
+        let s = "aaa κόσμε aaaaaaaaaaaaaaaaaaaaa κόσμε xxx 𐆚𐆚𐆚";
+        let rope = rope_with_gaps(s, 15);
+
+        for pattern in ["", "a", "aa", "aaa", "κόσμε", "σμε a", "𐆚", "𐆚𐆚", " ", "nope", s] {
+            check_search(&rope, s, pattern);
+        }
+    }
+
+    #[test]
+    fn rfind_across_last_chunk() {
+        // Put the needle over the start of the last chunk, so it spans two chunks. The needle is
+        // ASCII so the chunks are the same as in the plain rope.
+        let plain = "xxxxxxxxxx".repeat(300);
+        let last_chunk = JumpRope::from(plain.as_str()).substrings_with_len().last().unwrap().1;
+        let start = plain.len() - last_chunk - 2;
+        let s = format!("xxab!?{}ab!?{}", &plain[6..start], &plain[start + 4..]);
+
+        let rope = JumpRope::from(s.as_str());
+        assert!(rope.substrings().last().unwrap().starts_with("!?"));
+
+        for pattern in ["ab!?", "ab", "!?", "!?x", "xa", "?"] {
+            check_search(&rope, &s, pattern);
+        }
+        assert_eq!(rope.rfind("ab!?"), Some(start));
+    }
+
+    #[test]
+    fn search_random() {
+        let mut rng = SmallRng::seed_from_u64(10);
+        let alphabet = ['a', 'b', 'ό', '𐆚'];
+
+        for _i in 0..50 {
+            let len = rng.gen_range(0..100);
+            let s = (0..len).map(|_| alphabet[rng.gen_range(0..alphabet.len())]).collect::<String>();
+            let rope = JumpRope::from(s.as_str());
+
+            for _j in 0..5 {
+                let len = rng.gen_range(1..5);
+                let pattern = (0..len).map(|_| alphabet[rng.gen_range(0..alphabet.len())]).collect::<String>();
+                check_search(&rope, &s, &pattern);
+            }
+        }
+    }
+}
@@ -0,0 +1,115 @@
+use std::fmt;
+use serde::de::{Error, Unexpected, Visitor};
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use crate::{JumpRope, JumpRopeBuf};
+
+/// Ropes are serialized as strings in every format, so a rope can replace a `String` field without
+/// changing the serialized data. Equal ropes always serialize to the same output, regardless of
+/// how their content is split up internally.
+///
+/// The rope's contents are streamed into the serializer through [`Serializer::collect_str`], which
+/// (for serializers like serde_json) avoids building a `String` copy of the document first.
+#[cfg_attr(doc_cfg, doc(cfg(feature = "serde")))]
+impl Serialize for JumpRope {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+/// Any pending buffered edit is flushed into the rope before it is serialized.
+#[cfg_attr(doc_cfg, doc(cfg(feature = "serde")))]
+impl Serialize for JumpRopeBuf {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        self.borrow().serialize(serializer)
+    }
+}
+
+struct RopeVisitor;
+
+impl<'de> Visitor<'de> for RopeVisitor {
+    type Value = JumpRope;
+
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        formatter.write_str("a string")
+    }
+
+    fn visit_str<E: Error>(self, v: &str) -> Result<JumpRope, E> {
+        Ok(JumpRope::new_from_str_bulk(v))
+    }
+
+    fn visit_bytes<E: Error>(self, v: &[u8]) -> Result<JumpRope, E> {
+        match std::str::from_utf8(v) {
+            Ok(s) => Ok(JumpRope::new_from_str_bulk(s)),
+            Err(_) => Err(E::invalid_value(Unexpected::Bytes(v), &self)),
+        }
+    }
+}
+
+/// Ropes are deserialized from strings. The rope is built by filling up nodes in order, without
+/// searching the rope for each insert.
+#[cfg_attr(doc_cfg, doc(cfg(feature = "serde")))]
+impl<'de> Deserialize<'de> for JumpRope {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        deserializer.deserialize_str(RopeVisitor)
+    }
+}
+
+#[cfg_attr(doc_cfg, doc(cfg(feature = "serde")))]
+impl<'de> Deserialize<'de> for JumpRopeBuf {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        JumpRope::deserialize(deserializer).map(JumpRopeBuf::with_rope)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_test::{assert_de_tokens, assert_de_tokens_error, assert_tokens, Compact, Configure, Token};
+    use crate::{JumpRope, JumpRopeBuf};
+
+    #[test]
+    fn serde_round_trip() {
+        let s = "κό𝕐𝕆😘σμε \"hi\" there\nthis is long enough to span several nodes";
+        let rope = JumpRope::from(s);
+
+        let json = serde_json::to_string(&rope).unwrap();
+        assert_eq!(json, serde_json::to_string(s).unwrap());
+
+        let rope2: JumpRope = serde_json::from_str(&json).unwrap();
+        rope2.check();
+        assert_eq!(rope2, s);
+
+        // Strings with escapes can't be borrowed from the input.
+        let rope3: JumpRope = serde_json::from_reader(json.as_bytes()).unwrap();
+        assert_eq!(rope3, s);
+    }
+
+    #[test]
+    fn serde_compact() {
+        // Compact formats store a single string too, however the rope's content is split up.
+        let mut rope = JumpRope::from("hi there");
+        rope.insert(2, "!");
+        assert!(rope.substrings().count() > 1);
+        assert_tokens(&rope.compact(), &[Token::Str("hi! there")]);
+        assert_tokens(&JumpRope::new().compact(), &[Token::Str("")]);
+        assert_tokens(&JumpRope::from("hi").readable(), &[Token::Str("hi")]);
+
+        assert_de_tokens(&JumpRope::from("κό𝕐𝕆").compact(), &[Token::Bytes("κό𝕐𝕆".as_bytes())]);
+        assert_de_tokens_error::<Compact<JumpRope>>(&[Token::Bytes(b"\xff")],
+            "invalid value: byte array, expected a string");
+        assert_de_tokens_error::<Compact<JumpRope>>(&[Token::Seq { len: Some(0) }],
+            "invalid type: sequence, expected a string");
+    }
+
+    #[test]
+    fn serde_buffered() {
+        let mut rope = JumpRopeBuf::new_from_str("hi there");
+        rope.insert(2, " you");
+        rope.remove(0..1);
+
+        let json = serde_json::to_string(&rope).unwrap();
+        assert_eq!(json, r#""i you there""#);
+
+        let rope2: JumpRopeBuf = serde_json::from_str(&json).unwrap();
+        assert_eq!(rope2, "i you there");
+    }
+}
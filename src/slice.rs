@@ -0,0 +1,260 @@
+use std::fmt::{Debug, Display, Formatter};
+use std::ops::Range;
+use crate::iter::{BytesInRange, CharsInRange, SliceIter, SubstringsInRange, Utf16UnitsInRange};
+use crate::jumprope::ReadCursor;
+use crate::JumpRope;
+
+/// A borrowed view of a range of unicode characters within a [`JumpRope`].
+///
+/// Slices are cheap to create and copy. They are created with [`JumpRope::slice`], and hold a
+/// cursor into the rope pointing to the start of the range. The rope cannot be modified while any
+/// slices into it are alive.
+///
+/// # Example
+///
+/// ```
+/// # use jumprope::*;
+/// let rope = JumpRope::from("xxxGreetings!xxx");
+/// let slice = rope.slice(3..13);
+/// assert_eq!(slice, "Greetings!");
+/// assert_eq!(slice.slice(0..5), "Greet");
+/// ```
+#[derive(Clone, Copy)]
+pub struct RopeSlice<'a> {
+    rope: &'a JumpRope,
+
+    /// A cursor pointing to the start of the slice.
+    cursor: ReadCursor<'a>,
+
+    /// The character position of the start of the slice in the rope.
+    start: usize,
+
+    len_chars: usize,
+    len_bytes: usize,
+}
+
+impl<'a> RopeSlice<'a> {
+    /// Return the length of the slice in unicode characters.
+    ///
+    /// This method returns the length in constant-time (*O(1)*).
+    pub fn len_chars(&self) -> usize {
+        self.len_chars
+    }
+
+    /// Get the number of bytes used for the UTF8 representation of the slice.
+    ///
+    /// This method returns the length in constant-time (*O(1)*).
+    pub fn len_bytes(&self) -> usize {
+        self.len_bytes
+    }
+
+    /// Returns `true` if the slice contains no characters.
+    pub fn is_empty(&self) -> bool {
+        self.len_chars == 0
+    }
+
+    /// Returns the unicode character range of the rope named by this slice.
+    pub fn char_range(&self) -> Range<usize> {
+        self.start..self.start + self.len_chars
+    }
+
+    /// Iterate over the slice, visiting each substring in [`str`] chunks along with its length in
+    /// unicode characters.
+    ///
+    /// The same stability warning applies as [`JumpRope::substrings_with_len`].
+    pub fn substrings_with_len(&self) -> SliceIter<'a> {
+        SliceIter::from_cursor(self.rope, &self.cursor, self.start..self.start + self.len_chars)
+    }
+
+    /// Iterate over the slice, visiting each substring in [`str`] chunks.
+    ///
+    /// The same stability warning applies as [`JumpRope::substrings`].
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use jumprope::*;
+    /// let rope = JumpRope::from("xxxGreetings!xxx");
+    /// let mut string = String::new();
+    /// for s in rope.slice(3..13).substrings() {
+    ///     string.push_str(s);
+    /// }
+    /// assert_eq!(string, "Greetings!");
+    /// ```
+    pub fn substrings(&self) -> SubstringsInRange<'a> {
+        self.substrings_with_len().substrings()
+    }
+
+    /// Get an iterator over all characters in the slice.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use jumprope::*;
+    /// let rope = JumpRope::from("xxxGreetings!xxx");
+    /// assert_eq!(rope.slice(3..13).chars().collect::<String>(), "Greetings!");
+    /// ```
+    pub fn chars(&self) -> CharsInRange<'a> {
+        self.substrings_with_len().chars()
+    }
+
+    /// Get an iterator over the bytes in the UTF-8 encoding of the slice.
+    pub fn bytes(&self) -> BytesInRange<'a> {
+        self.substrings_with_len().bytes()
+    }
+
+    /// Get an iterator over the UTF-16 code units (wchars) in the slice.
+    pub fn utf16_units(&self) -> Utf16UnitsInRange<'a> {
+        self.substrings_with_len().utf16_units()
+    }
+
+    /// Create a slice of a sub-range of this slice. The range is specified in unicode characters,
+    /// relative to the start of this slice.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the range is reversed or extends past the end of this slice.
+    pub fn slice(&self, range: Range<usize>) -> RopeSlice<'a> {
+        assert!(range.end <= self.len_chars, "Range extends past the end of the slice");
+        self.rope.slice(self.start + range.start..self.start + range.end)
+    }
+
+    // Like JumpRope, this is implemented explicitly because its smaller and faster than going
+    // through the Display trait.
+    #[allow(clippy::inherent_to_string_shadow_display)]
+    pub fn to_string(&self) -> String {
+        let mut result = String::with_capacity(self.len_bytes);
+        for s in self.substrings() {
+            result.push_str(s);
+        }
+        result
+    }
+
+    fn eq_str(&self, mut other: &str) -> bool {
+        if self.len_bytes != other.len() { return false; }
+
+        for s in self.substrings() {
+            let (start, rem) = other.split_at(s.len());
+            if start != s { return false; }
+            other = rem;
+        }
+
+        true
+    }
+}
+
+impl JumpRope {
+    /// Create a borrowed view of the specified range of unicode characters in the rope. This runs
+    /// in *O(log n)* time.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the range is reversed or extends past the end of the rope.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use jumprope::*;
+    /// let rope = JumpRope::from("κόσμε");
+    /// let slice = rope.slice(1..3);
+    /// assert_eq!(slice.len_chars(), 2);
+    /// assert_eq!(slice.len_bytes(), 4);
+    /// assert_eq!(slice.to_string(), "όσ");
+    /// ```
+    pub fn slice(&self, range: Range<usize>) -> RopeSlice<'_> {
+        assert!(range.start <= range.end, "Slice range is reversed");
+        assert!(range.end <= self.len_chars(), "Range extends past the end of the rope");
+
+        let cursor = self.read_cursor_at_char(range.start, false);
+        let len_bytes = self.char_to_byte(range.end) - cursor.global_bytes;
+
+        RopeSlice {
+            rope: self,
+            cursor,
+            start: range.start,
+            len_chars: range.end - range.start,
+            len_bytes,
+        }
+    }
+}
+
+impl<'a> Debug for RopeSlice<'a> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.debug_list()
+            .entries(self.substrings())
+            .finish()
+    }
+}
+
+impl<'a> Display for RopeSlice<'a> {
+    fn fmt(&self, f: &mut Formatter) -> std::fmt::Result {
+        for s in self.substrings() {
+            f.write_str(s)?;
+        }
+        Ok(())
+    }
+}
+
+impl<'a> PartialEq<str> for RopeSlice<'a> {
+    fn eq(&self, other: &str) -> bool {
+        self.eq_str(other)
+    }
+}
+
+impl<'a> PartialEq<&str> for RopeSlice<'a> {
+    fn eq(&self, other: &&str) -> bool {
+        self.eq_str(other)
+    }
+}
+
+impl<'a> PartialEq<String> for RopeSlice<'a> {
+    fn eq(&self, other: &String) -> bool {
+        self.eq_str(other.as_str())
+    }
+}
+
+impl<'a, 'b> PartialEq<RopeSlice<'b>> for RopeSlice<'a> {
+    fn eq(&self, other: &RopeSlice<'b>) -> bool {
+        self.len_bytes == other.len_bytes && self.chars().eq(other.chars())
+    }
+}
+
+impl<'a> Eq for RopeSlice<'a> {}
+
+impl<'a> From<RopeSlice<'a>> for JumpRope {
+    fn from(slice: RopeSlice<'a>) -> Self {
+        let mut rope = JumpRope::new();
+        rope.extend(slice.substrings());
+        rope
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::JumpRope;
+
+    #[test]
+    fn slices_match_str() {
+        let s = "κό𝕐𝕆😘σμε hi there, this is long enough to span several nodes";
+        let rope = JumpRope::from(s);
+        let len = rope.len_chars();
+
+        for start in 0..=len {
+            for end in start..=len {
+                let byte_start = s.char_indices().nth(start).map_or(s.len(), |(b, _)| b);
+                let byte_end = s.char_indices().nth(end).map_or(s.len(), |(b, _)| b);
+                let expected = &s[byte_start..byte_end];
+
+                let slice = rope.slice(start..end);
+                assert_eq!(slice.len_chars(), end - start);
+                assert_eq!(slice.len_bytes(), expected.len());
+                assert_eq!(slice, expected);
+                assert_eq!(slice.chars().collect::<String>(), expected);
+                assert_eq!(format!("{}", slice), expected);
+
+                let sub = rope.slice(start..len).slice(0..end - start);
+                assert_eq!(sub, slice);
+            }
+        }
+    }
+}
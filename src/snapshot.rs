@@ -0,0 +1,307 @@
+//! A compact binary format for saving and loading ropes.
+//!
+//! The format is (with all integers stored little endian):
+//!
+//! - The magic bytes `JRSN`
+//! - The format version (1 byte). Currently always 1.
+//! - Flags (1 byte). Bit 0 is set if the number of UTF16 surrogate pairs is recorded. Bit 1 is set
+//!   if the number of newlines is recorded.
+//! - The length of the content in bytes (u64)
+//! - The length of the content in unicode characters (u64)
+//! - The number of UTF16 surrogate pairs (u64), if flagged
+//! - The number of newline characters (u64), if flagged
+//! - The UTF-8 content
+//! - A 64 bit FNV-1a checksum of everything before it (u64)
+//!
+//! Which optional counts are written depends on which features jumprope was compiled with. They're
+//! always validated when present.
+
+use std::io;
+use std::io::{Read, Write};
+use crate::fast_str_tools::{count_chars, count_utf16_surrogates};
+use crate::utils::count_lines;
+use crate::JumpRope;
+
+const MAGIC: [u8; 4] = *b"JRSN";
+const VERSION: u8 = 1;
+
+const FLAG_PAIRS: u8 = 1;
+const FLAG_LINES: u8 = 2;
+const KNOWN_FLAGS: u8 = FLAG_PAIRS | FLAG_LINES;
+
+/// FNV-1a. This is fast enough, and good enough to catch accidental corruption.
+struct Checksum(u64);
+
+impl Checksum {
+    fn new() -> Self {
+        Self(0xcbf29ce484222325)
+    }
+
+    fn update(&mut self, bytes: &[u8]) {
+        for &b in bytes {
+            self.0 ^= b as u64;
+            self.0 = self.0.wrapping_mul(0x100000001b3);
+        }
+    }
+}
+
+/// Wraps a writer, checksumming everything written through it.
+struct ChecksumWriter<W: Write> {
+    inner: W,
+    checksum: Checksum,
+}
+
+impl<W: Write> ChecksumWriter<W> {
+    fn write(&mut self, bytes: &[u8]) -> io::Result<()> {
+        self.checksum.update(bytes);
+        self.inner.write_all(bytes)
+    }
+
+    fn write_u64(&mut self, val: usize) -> io::Result<()> {
+        self.write(&(val as u64).to_le_bytes())
+    }
+}
+
+/// Wraps a reader, checksumming everything read through it.
+struct ChecksumReader<R: Read> {
+    inner: R,
+    checksum: Checksum,
+}
+
+impl<R: Read> ChecksumReader<R> {
+    fn read<const N: usize>(&mut self) -> io::Result<[u8; N]> {
+        let mut buf = [0; N];
+        self.inner.read_exact(&mut buf)?;
+        self.checksum.update(&buf);
+        Ok(buf)
+    }
+
+    fn read_u8(&mut self) -> io::Result<u8> {
+        Ok(self.read::<1>()?[0])
+    }
+
+    fn read_usize(&mut self) -> io::Result<usize> {
+        usize::try_from(u64::from_le_bytes(self.read()?))
+            .map_err(|_| invalid_data("snapshot is too large to load"))
+    }
+}
+
+fn invalid_data(msg: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, msg)
+}
+
+fn check_count(name: &str, expected: usize, actual: usize) -> io::Result<()> {
+    if expected == actual { Ok(()) } else {
+        Err(io::Error::new(io::ErrorKind::InvalidData,
+            format!("snapshot {} count mismatch: expected {}, found {}", name, expected, actual)))
+    }
+}
+
+impl JumpRope {
+    /// Write the rope to the passed writer using a compact, versioned binary format. The rope can
+    /// be loaded again using [`read_snapshot`](Self::read_snapshot).
+    ///
+    /// The snapshot contains the rope's contents, the recorded lengths of the rope and a checksum.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use jumprope::*;
+    /// let rope = JumpRope::from("κόσμε");
+    /// let mut data = vec![];
+    /// rope.write_snapshot(&mut data).unwrap();
+    ///
+    /// let loaded = JumpRope::read_snapshot(data.as_slice()).unwrap();
+    /// assert_eq!(loaded, rope);
+    /// ```
+    pub fn write_snapshot<W: Write>(&self, writer: W) -> io::Result<()> {
+        let mut flags = 0;
+        if cfg!(feature = "wchar_conversion") { flags |= FLAG_PAIRS; }
+        if cfg!(feature = "line_conversion") { flags |= FLAG_LINES; }
+
+        let mut w = ChecksumWriter { inner: writer, checksum: Checksum::new() };
+        w.write(&MAGIC)?;
+        w.write(&[VERSION, flags])?;
+        w.write_u64(self.len_bytes())?;
+        w.write_u64(self.len_chars())?;
+        #[cfg(feature = "wchar_conversion")] {
+            w.write_u64(self.len_wchars() - self.len_chars())?;
+        }
+        #[cfg(feature = "line_conversion")] {
+            w.write_u64(self.len_lines() - 1)?;
+        }
+
+        for s in self.substrings() {
+            w.write(s.as_bytes())?;
+        }
+
+        let checksum = w.checksum.0;
+        w.inner.write_all(&checksum.to_le_bytes())
+    }
+
+    /// Load a rope from a snapshot created with [`write_snapshot`](Self::write_snapshot).
+    ///
+    /// The rope's nodes are built directly from the snapshot contents, which is much faster than
+    /// inserting the text into a new rope. The content is checked against the snapshot's checksum
+    /// and recorded counts, and must be valid UTF-8.
+    ///
+    /// Returns an error of kind [`io::ErrorKind::InvalidData`] if the snapshot is corrupt or was
+    /// written by an unsupported version of jumprope.
+    pub fn read_snapshot<R: Read>(reader: R) -> io::Result<JumpRope> {
+        let mut r = ChecksumReader { inner: reader, checksum: Checksum::new() };
+
+        if r.read::<4>()? != MAGIC {
+            return Err(invalid_data("not a jumprope snapshot"));
+        }
+        let version = r.read_u8()?;
+        if version != VERSION {
+            return Err(io::Error::new(io::ErrorKind::InvalidData,
+                format!("unsupported snapshot version {}", version)));
+        }
+        let flags = r.read_u8()?;
+        if flags & !KNOWN_FLAGS != 0 {
+            return Err(invalid_data("unknown snapshot flags"));
+        }
+
+        let len_bytes = r.read_usize()?;
+        let len_chars = r.read_usize()?;
+        let num_pairs = if flags & FLAG_PAIRS != 0 { Some(r.read_usize()?) } else { None };
+        let num_lines = if flags & FLAG_LINES != 0 { Some(r.read_usize()?) } else { None };
+
+        // The length could be garbage, so don't trust it to preallocate the buffer.
+        let mut content = Vec::new();
+        (&mut r.inner).take(len_bytes as u64).read_to_end(&mut content)?;
+        if content.len() != len_bytes {
+            return Err(io::Error::new(io::ErrorKind::UnexpectedEof, "snapshot content is truncated"));
+        }
+        r.checksum.update(&content);
+
+        let expected_checksum = r.checksum.0;
+        let mut buf = [0; 8];
+        r.inner.read_exact(&mut buf)?;
+        if u64::from_le_bytes(buf) != expected_checksum {
+            return Err(invalid_data("snapshot checksum mismatch"));
+        }
+
+        let content = String::from_utf8(content)
+            .map_err(|_| invalid_data("snapshot content is not valid UTF-8"))?;
+
+        check_count("char", len_chars, count_chars(&content))?;
+        if let Some(num_pairs) = num_pairs {
+            check_count("surrogate pair", num_pairs, count_utf16_surrogates(&content))?;
+        }
+        if let Some(num_lines) = num_lines {
+            check_count("line", num_lines, count_lines(&content))?;
+        }
+
+        Ok(JumpRope::new_from_str_bulk(&content))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::ErrorKind;
+    use crate::JumpRope;
+    use crate::jumprope::NODE_STR_SIZE;
+    use crate::test_utils::SAMPLE;
+    use super::*;
+
+    fn snapshot(rope: &JumpRope) -> Vec<u8> {
+        let mut data = vec![];
+        rope.write_snapshot(&mut data).unwrap();
+        data
+    }
+
+    /// Replace the checksum at the end of a (modified) snapshot so it passes the checksum test.
+    fn reseal(data: &mut Vec<u8>) {
+        data.truncate(data.len() - 8);
+        let mut checksum = Checksum::new();
+        checksum.update(data);
+        data.extend_from_slice(&checksum.0.to_le_bytes());
+    }
+
+    fn load_err(data: &[u8]) -> ErrorKind {
+        JumpRope::read_snapshot(data).unwrap_err().kind()
+    }
+
+    #[test]
+    fn snapshot_round_trip() {
+        let exact = "x".repeat(NODE_STR_SIZE);
+        let multi = "κό".repeat(NODE_STR_SIZE);
+        for s in ["", "x", exact.as_str(), multi.as_str(), SAMPLE] {
+            let mut rope = JumpRope::from(s);
+            rope.insert(0, "abc");
+            rope.remove(0..3);
+
+            let loaded = JumpRope::read_snapshot(snapshot(&rope).as_slice()).unwrap();
+            loaded.check();
+            assert_eq!(loaded, s);
+
+            // The loaded rope should be editable as normal.
+            let mut loaded = loaded;
+            loaded.insert(1.min(loaded.len_chars()), "🐻‍❄️");
+            loaded.check();
+        }
+    }
+
+    #[test]
+    fn snapshot_empty() {
+        let data = snapshot(&JumpRope::new());
+        let num_counts = 2 + cfg!(feature = "wchar_conversion") as usize
+            + cfg!(feature = "line_conversion") as usize;
+        assert_eq!(data.len(), 4 + 2 + num_counts * 8 + 8);
+
+        let loaded = JumpRope::read_snapshot(data.as_slice()).unwrap();
+        loaded.check();
+        assert!(loaded.is_empty());
+    }
+
+    #[test]
+    fn snapshot_corruption() {
+        let data = snapshot(&JumpRope::from("hi there κόσμε"));
+
+        // Flip a byte anywhere and loading should fail.
+        for i in 0..data.len() {
+            let mut corrupt = data.clone();
+            corrupt[i] ^= 0x40;
+            assert!(JumpRope::read_snapshot(corrupt.as_slice()).is_err(), "byte {}", i);
+        }
+
+        for len in 0..data.len() {
+            assert!(JumpRope::read_snapshot(&data[..len]).is_err());
+        }
+        assert_eq!(load_err(&data[..0]), ErrorKind::UnexpectedEof);
+        assert_eq!(load_err(&data[..data.len() - 9]), ErrorKind::UnexpectedEof);
+
+        let mut wrong_version = data.clone();
+        wrong_version[4] = 2;
+        assert_eq!(load_err(&wrong_version), ErrorKind::InvalidData);
+    }
+
+    #[test]
+    fn snapshot_checked_after_checksum() {
+        // These snapshots have valid checksums, so only the other checks can catch them.
+        let data = snapshot(&JumpRope::from("hi κόσμε"));
+        let content_start = data.len() - 8 - "hi κόσμε".len();
+
+        let mut bad_flags = data.clone();
+        bad_flags[5] |= 4;
+        reseal(&mut bad_flags);
+        assert_eq!(load_err(&bad_flags), ErrorKind::InvalidData);
+
+        let mut bad_chars = data.clone();
+        bad_chars[14] += 1;
+        reseal(&mut bad_chars);
+        assert_eq!(load_err(&bad_chars), ErrorKind::InvalidData);
+
+        let mut bad_utf8 = data.clone();
+        bad_utf8[content_start + 3] = 0xff;
+        reseal(&mut bad_utf8);
+        assert_eq!(load_err(&bad_utf8), ErrorKind::InvalidData);
+
+        let mut too_large = data.clone();
+        too_large[6..14].copy_from_slice(&u64::MAX.to_le_bytes());
+        reseal(&mut too_large);
+        assert!(JumpRope::read_snapshot(too_large.as_slice()).is_err());
+    }
+}
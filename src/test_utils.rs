@@ -0,0 +1,24 @@
+//! Fixtures shared by the unit tests.
+
+use rand::prelude::*;
+use crate::JumpRope;
+
+/// Some text with multi-byte characters (including characters which need surrogate pairs in
+/// UTF-16) and newlines. This spans several nodes in test builds.
+pub(crate) const SAMPLE: &str = "κό𝕐𝕆😘σμε hi there\nthis is long enough to span several nodes\n";
+
+/// Pick some content to insert in a random edit.
+pub(crate) fn random_content(rng: &mut SmallRng) -> &'static str {
+    ["", "x", "𝕐𝕆", "ab\n", "κόσμε"][rng.gen_range(0..5)]
+}
+
+/// Create a rope containing `s`, with the gaps in its nodes moved around by re-inserting every
+/// `step`-th character.
+pub(crate) fn rope_with_gaps(s: &str, step: usize) -> JumpRope {
+    let mut rope = JumpRope::from(s);
+    for (i, c) in s.chars().enumerate().step_by(step) {
+        rope.remove(i..i + 1);
+        rope.insert(i, c.encode_utf8(&mut [0; 4]));
+    }
+    rope
+}
@@ -50,10 +50,25 @@ pub(crate) fn str_chars_to_bytes_rev(s: &str, char_len: usize) -> usize {
 //         .sum()
 // }
 
-#[cfg(feature = "line_conversion")]
 pub(crate) fn count_lines(s: &str) -> usize {
     // I'm sure there's faster implementations of this but this will do for now.
-    s.as_bytes().iter().filter(|b| **b == ('\n' as u8)).count()
+    s.as_bytes().iter().filter(|b| **b == b'\n').count()
+}
+
+/// Get the byte offset just after the `lines`th newline in s. Panics if s doesn't contain enough
+/// newlines.
+#[cfg(feature = "line_conversion")]
+pub(crate) fn str_lines_to_bytes(s: &str, lines: usize) -> usize {
+    if lines == 0 { return 0; }
+
+    let mut lines_remaining = lines;
+    for (i, byte) in s.as_bytes().iter().enumerate() {
+        if *byte == b'\n' {
+            lines_remaining -= 1;
+            if lines_remaining == 0 { return i+1; }
+        }
+    }
+    panic!("Insufficient lines in string");
 }
 
 #[cfg(test)]
@@ -85,5 +100,10 @@ mod tests {
         assert_eq!(count_lines(""), 0);
         assert_eq!(count_lines("\n"), 1);
         assert_eq!(count_lines("fop\n\n"), 2);
+
+        assert_eq!(str_lines_to_bytes("", 0), 0);
+        assert_eq!(str_lines_to_bytes("a\nbc\nd", 1), 2);
+        assert_eq!(str_lines_to_bytes("a\nbc\nd", 2), 5);
+        assert_eq!(str_lines_to_bytes("κ\n", 1), 3);
     }
 }
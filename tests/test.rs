@@ -6,7 +6,7 @@ use rand::prelude::*;
 use std::cmp::min;
 use std::ops::Range;
 use std::ptr;
-use jumprope::JumpRope;
+use jumprope::{JumpRope, CharBoundaryError};
 use jumprope::JumpRopeBuf;
 
 const UNI_CHARS: [char; 24] = [
@@ -55,6 +55,32 @@ fn check(r: &JumpRope, expected: &str) {
         // And if we convert back, we should get the number of characters.
         assert_eq!(r.wchars_to_chars(r.len_wchars()), r.len_chars());
     }
+    #[cfg(feature = "line_conversion")] {
+        assert_eq!(r.len_lines(), expected.matches('\n').count() + 1);
+
+        let mut line = 0;
+        assert_eq!(r.line_to_char(0), 0);
+        for (i, c) in expected.chars().enumerate() {
+            assert_eq!(r.char_to_line(i), line);
+            if c == '\n' {
+                line += 1;
+                assert_eq!(r.line_to_char(line), i + 1);
+            }
+        }
+        assert_eq!(r.char_to_line(r.len_chars()), line);
+        assert_eq!(r.line_to_char(r.len_lines()), r.len_chars());
+    }
+
+    for (i, (b, c)) in expected.char_indices().enumerate() {
+        assert_eq!(r.char_to_byte(i), b);
+        assert_eq!(r.byte_to_char(b), Ok(i));
+        for inner in b + 1..b + c.len_utf8() {
+            assert_eq!(r.byte_to_char(inner), Err(CharBoundaryError { byte_pos: inner }));
+        }
+    }
+    assert_eq!(r.char_to_byte(r.len_chars()), r.len_bytes());
+    assert_eq!(r.byte_to_char(r.len_bytes()), Ok(r.len_chars()));
+
     assert_eq!(*r, JumpRope::from(expected), "Rope comparison fails");
 
     let clone = r.clone();
@@ -434,6 +460,150 @@ fn fuzz_buffered_forever() {
     }
 }
 
+#[cfg(feature = "line_conversion")]
+#[test]
+fn line_col_editing() {
+    let mut r = JumpRope::from("abc\ndef\nghi");
+    check(&r, "abc\ndef\nghi");
+
+    assert_eq!(r.insert_at_line_col(1, 1, "XX\n"), 5);
+    check(&r, "abc\ndXX\nef\nghi");
+
+    // Columns past the end of a line insert at the end of that line.
+    assert_eq!(r.insert_at_line_col(0, 100, "!"), 3);
+    check(&r, "abc!\ndXX\nef\nghi");
+    assert_eq!(r.insert_at_line_col(2, 3, "?"), 11);
+    check(&r, "abc!\ndXX\nef?\nghi");
+    assert_eq!(r.insert_at_line_col(3, 100, "!"), r.len_chars() - 1);
+    check(&r, "abc!\ndXX\nef?\nghi!");
+    assert_eq!(r.insert_at_line_col(3, 5, "."), r.len_chars() - 1);
+    check(&r, "abc!\ndXX\nef?\nghi!.");
+
+    r.remove_line_range(1..3);
+    check(&r, "abc!\nghi!.");
+
+    r.remove_line_range(1..100);
+    check(&r, "abc!\n");
+
+    r.remove_line_range(0..1);
+    check(&r, "");
+}
+
+#[cfg(feature = "line_conversion")]
+#[test]
+#[should_panic]
+fn line_col_past_last_line() {
+    // Lines are zero-based, so this document only has lines 0 and 1.
+    let mut r = JumpRope::from("abc\ndef");
+    r.insert_at_line_col(2, 0, "x");
+}
+
+#[test]
+fn byte_editing() {
+    let mut r = JumpRope::from("κόσμε");
+    check(&r, "κόσμε");
+
+    assert_eq!(r.insert_at_byte(4, "abc"), Ok(2));
+    check(&r, "κόabcσμε");
+
+    // Positions inside a character are rejected, and the rope is left alone.
+    assert_eq!(r.insert_at_byte(1, "x"), Err(CharBoundaryError { byte_pos: 1 }));
+    assert_eq!(r.remove_bytes(4..8), Err(CharBoundaryError { byte_pos: 8 }));
+    assert_eq!(r.replace_bytes(3..5, "x"), Err(CharBoundaryError { byte_pos: 3 }));
+    check(&r, "κόabcσμε");
+
+    r.remove_bytes(5..9).unwrap();
+    check(&r, "κόaμε");
+
+    r.replace_bytes(0..2, "K").unwrap();
+    check(&r, "Kόaμε");
+
+    // Positions past the end of the document are truncated.
+    assert_eq!(r.insert_at_byte(100, "!"), Ok(5));
+    r.remove_bytes(4..100).unwrap();
+    check(&r, "Kόa");
+}
+
+#[test]
+fn split_off_and_append() {
+    let mut rng = SmallRng::seed_from_u64(321);
+
+    for _i in 0..100 {
+        let text = random_unicode_string(rng.gen_range(0..200), &mut rng);
+        let mut r = JumpRope::from(text.as_str());
+        let len = r.len_chars();
+        let pos = rng.gen_range(0..len + 1);
+        let byte_pos = text.char_indices().nth(pos).map_or(text.len(), |(b, _)| b);
+
+        let mut rest = r.split_off(pos);
+        check(&r, &text[..byte_pos]);
+        check(&rest, &text[byte_pos..]);
+        // The byte counts in the skip list are split along with the nodes.
+        assert_eq!(r.len_bytes(), byte_pos);
+        assert_eq!(rest.char_to_byte(rest.len_chars()), text.len() - byte_pos);
+
+        // Both halves should still be editable.
+        r.insert(0, "ab");
+        rest.insert(rest.len_chars(), "cd");
+        let expected = format!("ab{}cd", text);
+        check(&r, &expected[..byte_pos + 2]);
+        check(&rest, &expected[byte_pos + 2..]);
+
+        r.append(rest);
+        check(&r, &expected);
+
+        // Appending onto a rope which has been extended by other ropes should work too.
+        let extra = random_unicode_string(rng.gen_range(0..50), &mut rng);
+        r.append(JumpRope::from(extra.as_str()));
+        check(&r, &(expected + extra.as_str()));
+    }
+}
+
+#[test]
+fn remove_returning_deleted_text() {
+    let mut rng = SmallRng::seed_from_u64(123);
+    let mut r = JumpRope::new();
+    let mut s = String::new();
+
+    for _i in 0..400 {
+        let len = s.chars().count();
+        let pos = rng.gen_range(0..len + 1);
+        let dlen = min(rng.gen_range(0..30), len - pos);
+        let text = random_unicode_string(rng.gen_range(0..20), &mut rng);
+
+        let byte_range = char_range_to_byte_range(&s, pos..pos + dlen);
+        let expected_deleted = s[byte_range.clone()].to_string();
+
+        let deleted = if text.is_empty() || rng.gen_bool(0.5) {
+            string_del_at(&mut s, pos, dlen);
+            r.remove_returning(pos..pos + dlen)
+        } else {
+            s.replace_range(byte_range, &text);
+            r.replace_returning(pos..pos + dlen, &text)
+        };
+
+        assert_eq!(deleted, expected_deleted);
+        if s.len() < 200 { s.push_str(&text); r.insert(r.len_chars(), &text); }
+        check(&r, &s);
+    }
+}
+
+#[test]
+#[should_panic]
+fn remove_returning_reversed_range() {
+    let mut r = JumpRope::from("Whoa dawg!");
+    #[allow(clippy::reversed_empty_ranges)]
+    r.remove_returning(9..4);
+}
+
+#[test]
+#[should_panic]
+fn replace_reversed_range() {
+    let mut r = JumpRope::from("Whoa dawg!");
+    #[allow(clippy::reversed_empty_ranges)]
+    r.replace(9..4, "x");
+}
+
 #[test]
 fn eq_variants() {
     let rope = JumpRope::from("Hi there");